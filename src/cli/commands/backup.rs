@@ -1,32 +1,135 @@
 use crate::cli::validators;
-use crate::database::{get_mixxx_database_path, get_mixxx_directory};
+use crate::database::backup::{
+    self,
+    retention::{self, Policy},
+    Backup,
+};
+use crate::database::get_mixxx_database_path;
 use crate::error::MixxxkitExit;
-use chrono::Utc;
+use clap::{Parser, Subcommand};
 use inquire::validator::{StringValidator, Validation};
 use inquire::CustomUserError;
-use log::{error, info};
-use std::fs::{copy, create_dir_all};
+use log::{error, info, warn};
+use std::fs::remove_file;
 use std::path::Path;
 
-pub fn run() -> Result<(), CustomUserError> {
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    #[command(subcommand)]
+    pub action: Option<Action>,
+    #[command(flatten)]
+    pub retention: RetentionArgs,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Action {
+    /// List backups and whether the retention rules would keep them
+    List,
+    /// Delete backups that fall outside the retention rules
+    Prune {
+        /// Show which backups would be deleted without deleting them
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Retention rules applied after each backup. If none are given, every backup is kept.
+#[derive(Parser, Debug, Default)]
+pub struct RetentionArgs {
+    /// Keep the most recent backups
+    #[arg(long, global = true, env = "MIXXXKIT_KEEP_LAST", value_names = ["count"])]
+    pub keep_last: Option<usize>,
+    /// Keep the newest backup of each of the most recent days
+    #[arg(long, global = true, env = "MIXXXKIT_KEEP_DAILY", value_names = ["count"])]
+    pub keep_daily: Option<usize>,
+    /// Keep the newest backup of each of the most recent weeks
+    #[arg(long, global = true, env = "MIXXXKIT_KEEP_WEEKLY", value_names = ["count"])]
+    pub keep_weekly: Option<usize>,
+    /// Keep the newest backup of each of the most recent months
+    #[arg(long, global = true, env = "MIXXXKIT_KEEP_MONTHLY", value_names = ["count"])]
+    pub keep_monthly: Option<usize>,
+    /// Delete the oldest kept backups once their total size exceeds this (e.g. `500MB`)
+    #[arg(long, global = true, env = "MIXXXKIT_MAX_SIZE", value_parser = retention::parse_size, value_names = ["size"])]
+    pub max_size: Option<u64>,
+}
+
+impl RetentionArgs {
+    fn policy(&self) -> Policy {
+        Policy {
+            keep_last: self.keep_last,
+            keep_daily: self.keep_daily,
+            keep_weekly: self.keep_weekly,
+            keep_monthly: self.keep_monthly,
+            max_size: self.max_size,
+        }
+    }
+}
+
+pub async fn run(args: &Args) -> Result<(), CustomUserError> {
     let source = get_mixxx_database_path()?;
+    let policy = args.retention.policy();
+    match args.action {
+        None => create(&source, &policy).await,
+        Some(Action::List) => list(&source, &policy),
+        Some(Action::Prune { dry_run }) => prune(&source, &policy, dry_run),
+    }
+}
+
+async fn create(source: &Path, policy: &Policy) -> Result<(), CustomUserError> {
     let validation = validators::Database::Required.validate(&source.to_string_lossy())?;
     if Validation::Valid != validation {
         error!("Could not find Mixxx database");
         return Err(Box::new(MixxxkitExit::Abort));
     }
 
-    let mut target = get_mixxx_directory()?;
-    create_dir_all(&target)?;
-
-    let filename = Utc::now().format("%Y-%m-%d-%s.sqlite").to_string();
-    target.push(Path::new("backups"));
-    target.push(Path::new(&filename));
-    copy(&source, &target)?;
+    let target = backup::create(source).await?;
 
     info!(
-        r#"Successfully backed up to "{}""#,
+        r#"Successfully backed up and verified "{}""#,
         target.to_string_lossy()
     );
+    if !policy.is_empty() {
+        prune(source, policy, false)?;
+    }
+    Ok(())
+}
+
+fn list(source: &Path, policy: &Policy) -> Result<(), CustomUserError> {
+    let (kept, pruned) = retention::partition(backup::list(source)?, policy);
+    if kept.is_empty() && pruned.is_empty() {
+        info!("No backups found");
+        return Ok(());
+    }
+    let mut all: Vec<(&Backup, &str)> = kept
+        .iter()
+        .map(|backup| (backup, "keep"))
+        .chain(pruned.iter().map(|backup| (backup, "prune")))
+        .collect();
+    all.sort_by_key(|(backup, _)| std::cmp::Reverse(backup.created));
+    for (backup, status) in all {
+        info!("[{status:<5}] {backup}");
+    }
+    Ok(())
+}
+
+fn prune(source: &Path, policy: &Policy, dry_run: bool) -> Result<(), CustomUserError> {
+    if policy.is_empty() {
+        warn!("No retention rules were given, so no backups will be pruned");
+        return Ok(());
+    }
+    let (_, pruned) = retention::partition(backup::list(source)?, policy);
+    if pruned.is_empty() {
+        info!("No backups to prune");
+        return Ok(());
+    }
+    for backup in &pruned {
+        match dry_run {
+            true => info!("Would delete {backup}"),
+            false => {
+                remove_file(&backup.path)?;
+                info!("Deleted {backup}");
+            }
+        }
+    }
     Ok(())
 }
@@ -0,0 +1,317 @@
+use super::{guard, snapshot::Snapshot};
+use crate::database::codec::{Beats, Source};
+use crate::database::functions::{crates, tracks};
+use crate::database::schema::library;
+use crate::database::{get_mixxx_database_path, get_sqlite_connection};
+use crate::error::MixxxkitExit;
+use clap::Parser;
+use inquire::{Confirm, CustomType, CustomUserError, MultiSelect, Select};
+use log::{debug, error, info, warn};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, TransactionTrait};
+use std::collections::HashSet;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+#[derive(Parser, Clone, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Args {
+    /// Only edit tracks in these crates
+    #[arg(long = "crate")]
+    pub crates: Vec<String>,
+    /// Only edit tracks whose artist, title, album or genre contains this text
+    #[arg(long)]
+    pub search: Option<String>,
+    /// Only edit tracks of at least this BPM
+    #[arg(long)]
+    pub min_bpm: Option<f64>,
+    /// Only edit tracks of at most this BPM
+    #[arg(long)]
+    pub max_bpm: Option<f64>,
+    /// Move beats by this many milliseconds, later if positive and earlier if negative
+    #[arg(long, allow_negative_numbers = true)]
+    pub shift: Option<f64>,
+    /// Halve the BPM of tracks analysed at double time
+    #[arg(long, conflicts_with = "double")]
+    pub halve: bool,
+    /// Double the BPM of tracks analysed at half time
+    #[arg(long)]
+    pub double: bool,
+    /// Replace beats with a constant grid at the nearest whole BPM
+    #[arg(long)]
+    pub snap: bool,
+    /// Lock the BPM of edited tracks so Mixxx leaves their beats alone
+    #[arg(long)]
+    pub lock: bool,
+    /// Also edit tracks whose BPM is locked
+    #[arg(long)]
+    pub include_locked: bool,
+    /// Print what would change without saving anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Do not back up your installation database before editing it
+    #[arg(long)]
+    pub no_backup: bool,
+    /// Skip all prompts
+    #[arg(short, long)]
+    pub force: bool,
+    /// Edit the database even if Mixxx is running
+    #[arg(long)]
+    pub ignore_running: bool,
+}
+
+impl Args {
+    fn has_edits(&self) -> bool {
+        self.has_beat_edits() || self.lock
+    }
+
+    fn has_beat_edits(&self) -> bool {
+        self.shift.is_some() || self.halve || self.double || self.snap
+    }
+
+    fn has_filters(&self) -> bool {
+        !self.crates.is_empty()
+            || self.search.is_some()
+            || self.min_bpm.is_some()
+            || self.max_bpm.is_some()
+    }
+}
+
+/// Edit offered when none is passed as an option
+#[derive(Clone, Copy, Debug, Display, EnumIter)]
+enum Edit {
+    #[strum(to_string = "Shift beats earlier or later")]
+    Shift,
+    #[strum(to_string = "Halve the BPM")]
+    Halve,
+    #[strum(to_string = "Double the BPM")]
+    Double,
+    #[strum(to_string = "Snap to a constant grid at the nearest whole BPM")]
+    Snap,
+    #[strum(to_string = "Lock the BPM")]
+    Lock,
+}
+
+pub async fn run(args: &Args) -> Result<(), CustomUserError> {
+    let mut args = args.clone();
+    if !args.has_edits() {
+        if args.force {
+            error!(
+                "Nothing to edit! Pass at least one of --shift, --halve, --double, --snap or --lock."
+            );
+            return Err(Box::new(MixxxkitExit::Abort));
+        }
+        prompt_for_edit(&mut args)?;
+        if !args.has_filters() {
+            args.crates = prompt_for_crates().await?;
+        }
+    }
+    let args = &args;
+    if !args.has_filters()
+        && !args.force
+        && !args.dry_run
+        && !Confirm::new("No crate or filter given, edit the beats of every track? (y/n)")
+            .prompt()?
+    {
+        return Err(Box::new(MixxxkitExit::Abort));
+    }
+
+    let url = get_mixxx_database_path()?;
+    let snapshot = match args.dry_run {
+        true => Snapshot::none(),
+        false => {
+            guard::ensure_closed(&url, args.ignore_running).await?;
+            Snapshot::take(&url, args.no_backup).await?
+        }
+    };
+    let db = &get_sqlite_connection(&url.to_string_lossy()).await?;
+    let result = edit(db, args).await;
+    let edited = snapshot.finish(result)?;
+
+    match args.dry_run {
+        true => info!("Dry run finished, {edited} tracks would be edited"),
+        false => info!("Successfully edited the beats of {edited} tracks, {snapshot}"),
+    }
+    Ok(())
+}
+
+fn prompt_for_edit(args: &mut Args) -> Result<(), CustomUserError> {
+    match Select::new("How should the beats be edited?", Edit::iter().collect()).prompt()? {
+        Edit::Shift => {
+            let shift = CustomType::<f64>::new("By how many milliseconds?")
+                .with_help_message("Positive values move beats later, negative ones earlier")
+                .prompt()?;
+            args.shift = Some(shift);
+        }
+        Edit::Halve => args.halve = true,
+        Edit::Double => args.double = true,
+        Edit::Snap => args.snap = true,
+        Edit::Lock => args.lock = true,
+    }
+    Ok(())
+}
+
+async fn prompt_for_crates() -> Result<Vec<String>, CustomUserError> {
+    let url = get_mixxx_database_path()?;
+    let db = get_sqlite_connection(&url.to_string_lossy()).await?;
+    let mut names: Vec<_> = crates::get(&db)
+        .await?
+        .into_iter()
+        .map(|found| found.name)
+        .collect();
+    if names.is_empty() {
+        return Ok(names);
+    }
+    names.sort();
+    Ok(MultiSelect::new("Which crates should be edited?", names)
+        .with_help_message("Select none to edit every track")
+        .prompt()?)
+}
+
+async fn edit(db: &DatabaseConnection, args: &Args) -> Result<usize, CustomUserError> {
+    let txn = db.begin().await?;
+    let in_crates = crates::get_track_ids_by_names(&txn, &args.crates).await?;
+    let (mut edited, mut locked, mut missing) = (0, 0, 0);
+    for track in tracks::get(&txn).await? {
+        if !matches(&track, args, in_crates.as_ref()) {
+            continue;
+        }
+        let is_locked = track.bpm_lock.unwrap_or_default() != 0;
+        // Locking alone leaves the beats as they are, even where there are none
+        if !args.has_beat_edits() {
+            if !is_locked {
+                debug!(r#"Locking the BPM of track id "{}""#, track.id);
+                if !args.dry_run {
+                    tracks::set_column(&txn, track.id, library::Column::BpmLock, 1).await?;
+                }
+                edited += 1;
+            }
+            continue;
+        }
+        if is_locked && !args.include_locked {
+            locked += 1;
+            continue;
+        }
+        let beats = match (&track.beats_version, &track.beats) {
+            (Some(version), Some(data)) => Beats::decode(version, data),
+            _ => None,
+        };
+        let (Some(beats), Some(sample_rate)) = (beats, track.samplerate.filter(|rate| *rate > 0))
+        else {
+            debug!(r#"Track id "{}" has no beats that can be edited"#, track.id);
+            missing += 1;
+            continue;
+        };
+        let Some(beats) = apply(beats, args, sample_rate) else {
+            debug!(r#"Could not snap the beats of track id "{}""#, track.id);
+            missing += 1;
+            continue;
+        };
+        let bpm = beats.get_bpm(sample_rate).unwrap_or_default();
+        debug!(
+            r#"Track id "{}" goes from {:.2} to {bpm:.2} BPM, first beat at frame {}"#,
+            track.id,
+            track.bpm.unwrap_or_default(),
+            beats.get_first_beat().unwrap_or_default()
+        );
+        if !args.dry_run {
+            save(&txn, track.id, &beats, bpm, args.lock).await?;
+        }
+        edited += 1;
+    }
+    txn.commit().await?;
+    if locked > 0 {
+        info!("Skipped {locked} tracks with a locked BPM, pass --include-locked to edit them too");
+    }
+    if missing > 0 {
+        warn!("Skipped {missing} tracks without beats! Analyse them in Mixxx first, or run with --debug to list them.");
+    }
+    Ok(edited)
+}
+
+fn matches(track: &library::Model, args: &Args, in_crates: Option<&HashSet<i32>>) -> bool {
+    if track.mixxx_deleted.unwrap_or_default() != 0
+        || in_crates.is_some_and(|ids| !ids.contains(&track.id))
+    {
+        return false;
+    }
+    let bpm = track.bpm.unwrap_or_default();
+    if args.min_bpm.is_some_and(|min| bpm < min) || args.max_bpm.is_some_and(|max| bpm > max) {
+        return false;
+    }
+    let Some(search) = &args.search else {
+        return true;
+    };
+    let search = search.to_lowercase();
+    [&track.artist, &track.title, &track.album, &track.genre]
+        .into_iter()
+        .flatten()
+        .any(|text| text.to_lowercase().contains(&search))
+}
+
+/// Halve or double first so that snapping rounds the corrected tempo, and
+/// shift last so the offset is relative to the final first beat. The edited
+/// beats count as set by the user rather than by whatever first produced them
+fn apply(mut beats: Beats, args: &Args, sample_rate: i32) -> Option<Beats> {
+    if args.halve {
+        beats.halve();
+    }
+    if args.double {
+        beats.double();
+    }
+    if args.snap {
+        beats = beats.snap(sample_rate)?;
+    }
+    if let Some(shift) = args.shift {
+        beats.shift(to_frames(shift, sample_rate));
+    }
+    beats.set_source(Source::User);
+    Some(beats)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_frames(milliseconds: f64, sample_rate: i32) -> i32 {
+    (milliseconds / 1000.0 * f64::from(sample_rate)).round() as i32
+}
+
+async fn save<C: ConnectionTrait>(
+    db: &C,
+    id: i32,
+    beats: &Beats,
+    bpm: f64,
+    lock: bool,
+) -> Result<(), DbErr> {
+    tracks::set_column(db, id, library::Column::Beats, beats.encode()).await?;
+    tracks::set_column(db, id, library::Column::BeatsVersion, beats.get_version()).await?;
+    tracks::set_column(db, id, library::Column::Bpm, bpm).await?;
+    if lock {
+        tracks::set_column(db, id, library::Column::BpmLock, 1).await?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn halves_before_snapping_and_shifting() {
+        let args = Args {
+            halve: true,
+            snap: true,
+            shift: Some(-10.0),
+            ..Args::default()
+        };
+        let beats = Beats::Grid {
+            bpm: 255.8,
+            first_beat: 1000,
+            source: Source::Analyzer,
+        };
+        assert_eq!(
+            apply(beats, &args, 44100),
+            Some(Beats::Grid {
+                bpm: 128.0,
+                first_beat: 559,
+                source: Source::User,
+            })
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use crate::cli::traits::{NormalizePath, ResolveBase};
+use crate::database::codec::{Beats, Keys};
+use crate::database::functions::{crates, cues, locations, playlists, tracks};
+use crate::database::get_sqlite_connection;
+use crate::database::schema::{
+    crate_tracks, crates as crate_schema, cues as cue_schema, library as library_schema,
+    playlist_tracks, playlists as playlist_schema,
+};
+use crate::error::MixxxkitExit;
+use crate::formats::{self, Cue, CueKind, Library, Playlist, Tags, Track};
+use clap::Parser;
+use inquire::{Confirm, CustomUserError, Text};
+use log::info;
+use std::collections::HashMap;
+use std::env::current_dir;
+use std::path::Path;
+
+/// Folders that crates and playlists are placed in, as other applications do
+/// not tell the two apart
+const CRATES_FOLDER: &str = "Crates";
+const PLAYLISTS_FOLDER: &str = "Playlists";
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// File to write the collection to. If omitted, you will be prompted.
+    pub output: Option<String>,
+}
+
+/// Writes the collection of one application
+pub type Writer = fn(&Path, &Library) -> formats::Result<()>;
+
+pub async fn run(
+    args: &Args,
+    write: Writer,
+    database: &str,
+    force: bool,
+) -> Result<(), CustomUserError> {
+    let output = match &args.output {
+        Some(path) => path.clone().normalize_path(),
+        None => Text::new("Path to write collection to:")
+            .prompt()?
+            .normalize_path(),
+    };
+    let output = output.resolve_base(current_dir()?);
+    if output.exists()
+        && !force
+        && !Confirm::new(&format!(
+            r#""{}" already exists. Overwrite it? (y/n)"#,
+            output.to_string_lossy()
+        ))
+        .prompt()?
+    {
+        return Err(Box::new(MixxxkitExit::Abort));
+    }
+
+    let db = get_sqlite_connection(database).await?;
+    let locations = locations::get(&db)
+        .await?
+        .into_iter()
+        .filter_map(|location| Some((location.id, location.location?)))
+        .collect();
+    let (mut library, ids) = get_tracks(tracks::get(&db).await?, &locations, cues::get(&db).await?);
+    library.playlists = get_playlists(
+        &ids,
+        crates::get(&db).await?,
+        crates::get_tracks(&db).await?,
+        playlists::get(&db).await?,
+        playlists::get_tracks(&db).await?,
+    );
+    write(&output, &library)?;
+
+    info!(
+        r#"Successfully exported {} tracks and {} playlists to "{}""#,
+        library.tracks.len(),
+        library.playlists.len(),
+        output.to_string_lossy()
+    );
+    Ok(())
+}
+
+/// Tracks that are still in the library, along with their ids in Mixxx
+fn get_tracks(
+    found: Vec<library_schema::Model>,
+    locations: &HashMap<i32, String>,
+    found_cues: Vec<cue_schema::Model>,
+) -> (Library, Vec<i32>) {
+    let mut by_track = HashMap::<i32, Vec<cue_schema::Model>>::new();
+    for cue in found_cues {
+        by_track.entry(cue.track_id).or_default().push(cue);
+    }
+    let mut library = Library::default();
+    let mut ids = Vec::new();
+    for track in found {
+        if track.mixxx_deleted.unwrap_or_default() != 0 {
+            continue;
+        }
+        let Some(location) = track.location.and_then(|id| locations.get(&id)) else {
+            continue;
+        };
+        let sample_rate = track.samplerate.filter(|rate| *rate > 0);
+        let first_beat = match (&track.beats_version, &track.beats, sample_rate) {
+            (Some(version), Some(beats), Some(rate)) => Beats::decode(version, beats)
+                .and_then(|beats| beats.get_first_beat())
+                .map(|frame| f64::from(frame) / f64::from(rate)),
+            _ => None,
+        };
+        // Keys read from the tags of a file keep their text in the key map
+        let key = track.key.filter(|key| !key.is_empty()).or_else(|| {
+            Keys::decode(track.keys_version.as_deref()?, track.keys.as_deref()?)?.global_key_text
+        });
+        let cues = match sample_rate {
+            Some(rate) => by_track
+                .remove(&track.id)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(|cue| to_cue(cue, rate))
+                .collect(),
+            None => Vec::new(),
+        };
+        library.tracks.push(Track {
+            location: location.clone(),
+            rating: track.rating.filter(|rating| *rating > 0),
+            color: track.color,
+            play_count: track.timesplayed,
+            sample_rate,
+            cues,
+            tags: Tags {
+                title: track.title,
+                artist: track.artist,
+                album: track.album,
+                genre: track.genre,
+                key,
+                duration: track.duration,
+                bpm: track.bpm.filter(|bpm| *bpm > 0.0),
+                first_beat,
+            },
+            ..Track::default()
+        });
+        ids.push(track.id);
+    }
+    (library, ids)
+}
+
+/// Mixxx stores positions as interleaved stereo samples regardless of the
+/// channels of the file, and has cue types that other applications lack
+fn to_cue(cue: &cue_schema::Model, sample_rate: i32) -> Option<Cue> {
+    let samples_per_second = f64::from(sample_rate) * 2.0;
+    let start = f64::from(cue.position) / samples_per_second;
+    let (kind, end) = match cue.r#type {
+        cues::TYPE_MAIN => (CueKind::Main, None),
+        cues::TYPE_HOTCUE => (CueKind::HotCue, None),
+        cues::TYPE_LOOP if cue.length > 0 => (
+            CueKind::Loop,
+            Some(f64::from(cue.position + cue.length) / samples_per_second),
+        ),
+        _ => return None,
+    };
+    Some(Cue {
+        kind,
+        start: start.max(0.0),
+        end,
+        hotcue: Some(cue.hotcue).filter(|hotcue| *hotcue >= 0 && kind != CueKind::Main),
+        label: cue.label.clone(),
+        color: Some(cue.color & 0x00FF_FFFF),
+    })
+}
+
+/// Crates and visible playlists in their own folders
+fn get_playlists(
+    ids: &[i32],
+    mut found_crates: Vec<crate_schema::Model>,
+    found_crate_tracks: Vec<crate_tracks::Model>,
+    found_playlists: Vec<playlist_schema::Model>,
+    found_playlist_tracks: Vec<playlist_tracks::Model>,
+) -> Vec<Playlist> {
+    let by_id: HashMap<i32, usize> = (0..).zip(ids).map(|(i, id)| (*id, i)).collect();
+    let mut crate_entries = HashMap::<i32, Vec<usize>>::new();
+    for entry in found_crate_tracks {
+        if let Some(index) = by_id.get(&entry.track_id) {
+            crate_entries
+                .entry(entry.crate_id)
+                .or_default()
+                .push(*index);
+        }
+    }
+    let mut playlist_entries = HashMap::<i32, Vec<usize>>::new();
+    for entry in found_playlist_tracks {
+        let (Some(playlist_id), Some(track_id)) = (entry.playlist_id, entry.track_id) else {
+            continue;
+        };
+        if let Some(index) = by_id.get(&track_id) {
+            playlist_entries
+                .entry(playlist_id)
+                .or_default()
+                .push(*index);
+        }
+    }
+
+    found_crates.sort_by(|a, b| a.name.cmp(&b.name));
+    let crates = found_crates.into_iter().map(|found| Playlist {
+        path: vec![CRATES_FOLDER.to_owned(), found.name],
+        entries: crate_entries.remove(&found.id).unwrap_or_default(),
+    });
+    let playlists = found_playlists
+        .into_iter()
+        .filter(|found| found.hidden == playlists::HIDDEN_NONE)
+        .map(|found| Playlist {
+            path: vec![PLAYLISTS_FOLDER.to_owned(), found.name.unwrap_or_default()],
+            entries: playlist_entries.remove(&found.id).unwrap_or_default(),
+        });
+    crates.chain(playlists).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_loop_to_seconds() {
+        let cue = cue_schema::Model {
+            id: 1,
+            track_id: 7,
+            r#type: cues::TYPE_LOOP,
+            position: 96000,
+            length: 48000,
+            hotcue: 3,
+            label: "Roll".into(),
+            color: 0x00FF_8800,
+        };
+        assert_eq!(
+            to_cue(&cue, 48000),
+            Some(Cue {
+                kind: CueKind::Loop,
+                start: 1.0,
+                end: Some(1.5),
+                hotcue: Some(3),
+                label: "Roll".into(),
+                color: Some(0x00FF_8800),
+            })
+        );
+    }
+}
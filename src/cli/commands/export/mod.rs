@@ -0,0 +1,298 @@
+mod library;
+
+use crate::cli::traits::{NormalizePath, ResolveBase};
+use crate::cli::validators;
+use crate::database::functions::crates::{self, Entry};
+use crate::database::{get_mixxx_database_path, get_sqlite_connection};
+use crate::error::MixxxkitExit;
+use crate::formats;
+use clap::{Parser, Subcommand, ValueEnum};
+use inquire::validator::StringValidator;
+use inquire::{CustomUserError, MultiSelect, Select, Text};
+use log::{error, info, warn};
+use std::collections::HashSet;
+use std::env::current_dir;
+use std::fmt::Write;
+use std::fs::{create_dir_all, write};
+use std::path::{Component, Path, PathBuf};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+#[derive(Parser, Debug, Default)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct Args {
+    #[command(subcommand)]
+    pub format: Option<Format>,
+    /// Folder to write playlists into. If omitted, you will be prompted.
+    pub output: Option<String>,
+    /// Name of a crate to export, may be repeated. If omitted, you will be prompted.
+    #[arg(short, long = "crate", value_names = ["name"])]
+    pub crates: Vec<String>,
+    /// How to write track paths. If omitted, you will be prompted.
+    #[arg(long, value_enum)]
+    pub paths: Option<PathStyle>,
+    /// Database to export from. If omitted, your installation database is used.
+    #[arg(long, global = true)]
+    pub database: Option<String>,
+    /// Skip all prompts, export everything and overwrite existing files
+    #[arg(short, long, global = true)]
+    pub force: bool,
+}
+
+/// Collections of other applications to export to instead of m3u8 playlists
+#[derive(Subcommand, Debug)]
+pub enum Format {
+    /// Export your library, crates and playlists as a Rekordbox XML collection
+    Rekordbox(library::Args),
+    /// Export your library, crates and playlists as a Traktor collection.nml
+    Traktor(library::Args),
+}
+
+/// How track paths are written into exported playlists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Display, EnumIter)]
+pub enum PathStyle {
+    /// Full paths as stored in the library
+    #[default]
+    #[strum(to_string = "Use absolute paths")]
+    Absolute,
+    /// Paths relative to the output folder, falling back to absolute paths on another drive
+    #[strum(to_string = "Use paths relative to the output folder")]
+    Relative,
+}
+
+pub async fn run(args: &Args) -> Result<(), CustomUserError> {
+    let (library_args, write): (_, library::Writer) = match &args.format {
+        Some(Format::Rekordbox(library_args)) => (library_args, formats::rekordbox::write),
+        Some(Format::Traktor(library_args)) => (library_args, formats::traktor::write),
+        None => return run_playlists(args).await,
+    };
+    let database = get_database(args.database.as_deref())?;
+    library::run(library_args, write, &database, args.force).await
+}
+
+/// The database given with `--database`, or the one of your installation
+fn get_database(path: Option<&str>) -> Result<String, CustomUserError> {
+    match path {
+        Some(path) => {
+            validators::Database::Required.validate(path)?;
+            Ok(path.to_owned())
+        }
+        None => Ok(get_mixxx_database_path()?.to_string_lossy().to_string()),
+    }
+}
+
+async fn run_playlists(args: &Args) -> Result<(), CustomUserError> {
+    let database = get_database(args.database.as_deref())?;
+    let db = get_sqlite_connection(&database).await?;
+
+    let mut available = crates::get(&db).await?;
+    available.sort_by(|a, b| a.name.cmp(&b.name));
+    let chosen = match (args.crates.is_empty(), args.force) {
+        (false, _) => {
+            let mut chosen = Vec::with_capacity(args.crates.len());
+            for name in &args.crates {
+                let Some(found) = available.iter().find(|found| &found.name == name) else {
+                    error!(r#"Could not find crate "{name}""#);
+                    return Err(Box::new(MixxxkitExit::Abort));
+                };
+                chosen.push(found.clone());
+            }
+            chosen
+        }
+        (true, true) => available,
+        (true, false) => {
+            let names = available.iter().map(|found| found.name.clone()).collect();
+            let all: Vec<_> = (0..available.len()).collect();
+            let picked = MultiSelect::new("Which crates would you like to export?", names)
+                .with_default(&all)
+                .prompt()?;
+            available
+                .into_iter()
+                .filter(|found| picked.contains(&found.name))
+                .collect()
+        }
+    };
+
+    let output = match &args.output {
+        Some(path) => path.clone().normalize_path(),
+        None => Text::new("Path to output folder:")
+            .with_help_message("Playlists will be written here, the folder is created if missing")
+            .prompt()?
+            .normalize_path(),
+    };
+    let style = match (args.paths, args.force) {
+        (Some(style), _) => style,
+        (None, false) => Select::new(
+            "How should track paths be written?",
+            PathStyle::iter().collect(),
+        )
+        .prompt()?,
+        (None, true) => PathStyle::default(),
+    };
+
+    let folder = output.resolve_base(current_dir()?);
+    create_dir_all(&folder)?;
+    let mut written = 0;
+    let file_names = get_file_names(chosen.iter().map(|found| found.name.as_str()));
+    for (found, file_name) in chosen.into_iter().zip(file_names) {
+        let path = folder.join(file_name);
+        if path.exists() && !args.force {
+            warn!(
+                r#"Playlist "{}" already exists, pass --force to overwrite it! Skipping..."#,
+                path.to_string_lossy()
+            );
+            continue;
+        }
+        let entries = crates::get_entries(&db, found.id).await?;
+        write(&path, format_playlist(&entries, style, &folder))?;
+        info!(
+            r#"Exported crate "{}" with {} tracks to "{}""#,
+            found.name,
+            entries.len(),
+            path.to_string_lossy()
+        );
+        written += 1;
+    }
+
+    info!("Successfully exported {written} crates");
+    Ok(())
+}
+
+fn format_playlist(entries: &[Entry], style: PathStyle, folder: &Path) -> String {
+    let mut playlist = String::from("#EXTM3U\n");
+    for entry in entries {
+        let Some(location) = &entry.location else {
+            continue;
+        };
+        let path = match style {
+            PathStyle::Absolute => location.clone(),
+            PathStyle::Relative => get_relative_path(Path::new(location), folder)
+                .map_or_else(|| location.clone(), NormalizePath::normalize_path),
+        };
+        #[allow(clippy::cast_possible_truncation)]
+        let seconds = entry
+            .duration
+            .map_or(-1, |duration| duration.round() as i64);
+        let name = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) if !artist.is_empty() => format!("{artist} - {title}"),
+            (_, Some(title)) => title.clone(),
+            _ => Path::new(location)
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        let _ = write!(playlist, "#EXTINF:{seconds},{name}\n{path}\n");
+    }
+    playlist
+}
+
+/// Walk up from `base` until reaching a shared ancestor of `path`, or give up
+/// when the two do not share a root such as on different drives
+fn get_relative_path(path: &Path, base: &Path) -> Option<PathBuf> {
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    if path_components.peek() != base_components.peek()
+        || !matches!(
+            path_components.peek(),
+            Some(Component::Prefix(_) | Component::RootDir)
+        )
+    {
+        return None;
+    }
+    while path_components.peek().is_some() && path_components.peek() == base_components.peek() {
+        path_components.next();
+        base_components.next();
+    }
+    let mut relative: PathBuf = base_components.map(|_| Component::ParentDir).collect();
+    relative.extend(path_components);
+    Some(relative)
+}
+
+/// Replace characters that are not allowed in file names on common file systems
+fn get_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    format!("{}.m3u8", sanitized.trim())
+}
+
+/// Names that sanitize to the same file, ignoring case for file systems that
+/// do, get a numbered suffix instead of overwriting each other
+fn get_file_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    let mut taken = HashSet::new();
+    names
+        .into_iter()
+        .map(|name| {
+            let mut file_name = get_file_name(name);
+            let mut suffix = 2;
+            while !taken.insert(file_name.to_lowercase()) {
+                file_name = get_file_name(&format!("{name} ({suffix})"));
+                suffix += 1;
+            }
+            file_name
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(location: &str, artist: Option<&str>, title: Option<&str>) -> Entry {
+        Entry {
+            location: Some(location.to_owned()),
+            artist: artist.map(ToOwned::to_owned),
+            title: title.map(ToOwned::to_owned),
+            duration: Some(180.6),
+        }
+    }
+
+    #[test]
+    fn formats_extended_playlist() {
+        let entries = [
+            entry("/music/a.mp3", Some("Artist"), Some("Song")),
+            entry("/music/sub/b.flac", None, None),
+        ];
+        let playlist = format_playlist(&entries, PathStyle::Relative, Path::new("/music/lists"));
+        assert_eq!(
+            playlist,
+            "#EXTM3U\n#EXTINF:181,Artist - Song\n../a.mp3\n#EXTINF:181,b\n../sub/b.flac\n"
+        );
+    }
+
+    #[test]
+    fn relative_path_needs_shared_root() {
+        assert_eq!(
+            get_relative_path(Path::new("/music/a.mp3"), Path::new("/music")),
+            Some(PathBuf::from("a.mp3"))
+        );
+        assert_eq!(
+            get_relative_path(Path::new("/music/a.mp3"), Path::new("/home/dj/lists")),
+            Some(PathBuf::from("../../../music/a.mp3"))
+        );
+        assert_eq!(
+            get_relative_path(Path::new("/music/a.mp3"), Path::new("lists")),
+            None
+        );
+    }
+
+    #[test]
+    fn sanitizes_file_name() {
+        assert_eq!(
+            get_file_name("House / Techno: 2024?"),
+            "House _ Techno_ 2024_.m3u8"
+        );
+    }
+
+    #[test]
+    fn suffixes_colliding_file_names() {
+        assert_eq!(
+            get_file_names(["A/B", "A_B", "a:b", "C"]),
+            ["A_B.m3u8", "A_B (2).m3u8", "a_b (3).m3u8", "C.m3u8"]
+        );
+    }
+}
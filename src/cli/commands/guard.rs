@@ -0,0 +1,22 @@
+use crate::database::guard;
+use crate::error::MixxxkitExit;
+use inquire::CustomUserError;
+use log::{error, warn};
+use std::path::Path;
+
+/// Refuse to edit a database that Mixxx has open, since Mixxx would write its
+/// in-memory state back over our changes when it exits
+pub async fn ensure_closed(
+    database: impl AsRef<Path>,
+    ignore_running: bool,
+) -> Result<(), CustomUserError> {
+    let Some(usage) = guard::detect(database.as_ref()).await? else {
+        return Ok(());
+    };
+    if ignore_running {
+        warn!("Continuing even though {usage}");
+        return Ok(());
+    }
+    error!("Close Mixxx before editing its database, or pass --ignore-running to continue anyway: {usage}");
+    Err(Box::new(MixxxkitExit::MixxxRunning))
+}
@@ -33,15 +33,16 @@ async fn import_entry(
     base: impl AsRef<Path>,
     path: impl AsRef<Path>,
 ) {
-    if entry.is_err() {
-        let err = entry.unwrap_err();
-        warn!(
-            "Could not read an item in {}: {err:?}",
-            path.as_ref().to_string_lossy(),
-        );
-        return;
-    }
-    let buf = entry.unwrap().path();
+    let buf = match entry {
+        Ok(entry) => entry.path(),
+        Err(err) => {
+            warn!(
+                "Could not read an item in {}: {err:?}",
+                path.as_ref().to_string_lossy(),
+            );
+            return;
+        }
+    };
     if !buf.is_supported_audio_ext() {
         return;
     }
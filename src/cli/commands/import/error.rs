@@ -6,6 +6,8 @@ pub enum Error {
     Io(#[from] std::io::Error),
     #[error("Unable to parse mixxxkit.crates.yaml")]
     ParsingFailed,
+    #[error("Unable to parse playlist {0}: {1}")]
+    InvalidPlaylist(String, String),
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
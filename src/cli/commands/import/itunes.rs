@@ -0,0 +1,56 @@
+use super::library::{self, CachedTrack};
+use super::EditArgs;
+use crate::database::functions::itunes;
+use crate::database::{get_mixxx_database_path, get_sqlite_connection};
+use crate::error::MixxxkitExit;
+use crate::formats;
+use clap::Parser;
+use inquire::CustomUserError;
+use log::{error, info};
+
+#[derive(Parser, Debug, Default)]
+#[group(skip)]
+pub struct Args {
+    #[command(flatten)]
+    pub library: library::Args,
+    /// Read the iTunes library cached by Mixxx instead of `iTunes Music Library.xml`
+    #[arg(long, conflicts_with = "path")]
+    pub cached: bool,
+}
+
+pub async fn run(args: &Args, edit: EditArgs) -> Result<(), CustomUserError> {
+    if !args.cached {
+        return library::run(&args.library, formats::itunes::read, edit).await;
+    }
+
+    let url = get_mixxx_database_path()?;
+    let db = get_sqlite_connection(&url.to_string_lossy()).await?;
+    let tracks = itunes::get(&db)
+        .await?
+        .into_iter()
+        .map(|track| CachedTrack {
+            id: track.id,
+            location: track.location,
+            rating: track.rating,
+        });
+    let playlists = itunes::get_playlists(&db)
+        .await?
+        .into_iter()
+        .map(|playlist| (playlist.id, playlist.name.unwrap_or_default()));
+    let entries = itunes::get_playlist_tracks(&db)
+        .await?
+        .into_iter()
+        .map(|entry| (entry.playlist_id, entry.track_id));
+    let library = library::from_cache(tracks, playlists, entries);
+    db.close().await?;
+    if library.tracks.is_empty() {
+        error!("Mixxx has not cached an iTunes library! Enable it in Mixxx's library preferences, or pass the path to iTunes Music Library.xml instead.");
+        return Err(Box::new(MixxxkitExit::Abort));
+    }
+    info!(
+        "Read {} tracks and {} playlists from the iTunes library cached by Mixxx",
+        library.tracks.len(),
+        library.playlists.len()
+    );
+    library::import_library(&args.library, library, edit).await
+}
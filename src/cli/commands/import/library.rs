@@ -0,0 +1,445 @@
+use super::EditArgs;
+use crate::cli::commands::{guard, snapshot::Snapshot};
+use crate::cli::traits::NormalizePath;
+use crate::database::functions::{crates, cues, playlists, tracks};
+use crate::database::schema::{cues as cue_schema, library as library_schema};
+use crate::database::{disable_fk, enable_fk, get_mixxx_database_path, get_sqlite_connection};
+use crate::formats::{self, Cue, CueKind, DecodeFileUri, Library, Playlist, Track};
+use clap::{Parser, ValueEnum};
+use inquire::{CustomUserError, MultiSelect, Select, Text};
+use log::{debug, info, warn};
+use sea_orm::{ActiveValue, ConnectionTrait, DatabaseConnection, DbErr, TransactionTrait};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Sample rate assumed for cue positions when neither Mixxx nor the collection knows it
+const DEFAULT_SAMPLE_RATE: i32 = 44100;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// Path to the exported collection. If omitted, you will be prompted.
+    pub path: Option<String>,
+    /// Import playlists as crates or Mixxx playlists. If omitted, you will be prompted.
+    #[arg(long = "as", value_enum)]
+    pub target: Option<Target>,
+    /// Placed between the names of folders and the playlists inside them
+    #[arg(long, default_value = " / ")]
+    pub separator: String,
+    /// Only import playlists with these names, including their folders. If omitted, you will be prompted.
+    #[arg(long = "playlist")]
+    pub playlists: Vec<String>,
+    /// Overwrite ratings, colors, play counts and cues that are already set in Mixxx
+    #[arg(long)]
+    pub overwrite: bool,
+}
+
+/// What playlists of other applications become in Mixxx
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Display, EnumIter)]
+pub enum Target {
+    #[default]
+    #[strum(to_string = "Import playlists as crates")]
+    Crates,
+    #[strum(to_string = "Import playlists as playlists, keeping their order")]
+    Playlists,
+}
+
+/// Parses the collection of one application
+pub type Reader = fn(&Path) -> formats::Result<Library>;
+
+pub async fn run(args: &Args, read: Reader, edit: EditArgs) -> Result<(), CustomUserError> {
+    let path = match &args.path {
+        Some(path) => path.clone().normalize_path(),
+        None => Text::new("Path to exported collection:")
+            .prompt()?
+            .normalize_path(),
+    };
+    let library = read(&PathBuf::from(&path))?;
+    info!(
+        r#"Read {} tracks and {} playlists from "{path}""#,
+        library.tracks.len(),
+        library.playlists.len()
+    );
+    import_library(args, library, edit).await
+}
+
+/// Import a collection that has already been read
+pub async fn import_library(
+    args: &Args,
+    mut library: Library,
+    edit: EditArgs,
+) -> Result<(), CustomUserError> {
+    rename_duplicates(&mut library.playlists, &args.separator);
+    library.playlists = choose_playlists(args, library.playlists, edit.force)?;
+    let target = match (args.target, edit.force) {
+        (Some(target), _) => target,
+        (None, false) if !library.playlists.is_empty() => Select::new(
+            "How should playlists be imported?",
+            Target::iter().collect(),
+        )
+        .prompt()?,
+        (None, _) => Target::default(),
+    };
+
+    let url = get_mixxx_database_path()?;
+    guard::ensure_closed(&url, edit.ignore_running).await?;
+    let snapshot = Snapshot::take(&url, edit.no_backup).await?;
+    let db = &get_sqlite_connection(&url.to_string_lossy()).await?;
+    let result = Box::pin(import(db, &library, target, args)).await;
+    snapshot.finish(result)?;
+
+    info!("Successfully imported collection, {snapshot}");
+    Ok(())
+}
+
+async fn import(
+    db: &DatabaseConnection,
+    library: &Library,
+    target: Target,
+    args: &Args,
+) -> Result<(), CustomUserError> {
+    disable_fk(db).await?;
+    let txn = db.begin().await?;
+
+    let mut track_ids = Vec::with_capacity(library.tracks.len());
+    let mut missing = 0;
+    for track in &library.tracks {
+        let Some(found) = find_track(&txn, track).await? else {
+            debug!(r#"Could not find "{}" in database!"#, track.location);
+            missing += 1;
+            track_ids.push(None);
+            continue;
+        };
+        update_track(&txn, &found, track, args.overwrite).await?;
+        track_ids.push(Some(found.id));
+    }
+    if missing > 0 {
+        warn!(
+            "Could not find {missing} of {} tracks in your library! Try rescanning your library, or run with --debug to list them.",
+            library.tracks.len()
+        );
+    }
+
+    for playlist in &library.playlists {
+        let name = playlist.path.join(&args.separator);
+        let entries: Vec<i32> = playlist
+            .entries
+            .iter()
+            .filter_map(|index| track_ids.get(*index).copied().flatten())
+            .collect();
+        match target {
+            Target::Crates => import_crate(&txn, &name, &entries).await?,
+            Target::Playlists => import_playlist(&txn, &name, &entries).await?,
+        }
+    }
+
+    txn.commit().await?;
+    enable_fk(db).await?;
+    Ok(())
+}
+
+async fn find_track<C: ConnectionTrait>(
+    db: &C,
+    track: &Track,
+) -> Result<Option<library_schema::Model>, DbErr> {
+    for location in std::iter::once(&track.location).chain(&track.fallback_locations) {
+        if let Some(found) = tracks::get_by_location(db, location).await? {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+/// Track of an external library that Mixxx caches in its database
+pub struct CachedTrack {
+    pub id: i32,
+    pub location: Option<String>,
+    /// Stars from 0 to 5
+    pub rating: Option<i32>,
+}
+
+/// Convert one of the external library caches of Mixxx, given its playlists
+/// as ids and names and their entries as playlist and track ids in order
+pub fn from_cache(
+    tracks: impl IntoIterator<Item = CachedTrack>,
+    playlists: impl IntoIterator<Item = (i32, String)>,
+    entries: impl IntoIterator<Item = (Option<i32>, Option<i32>)>,
+) -> Library {
+    let mut library = Library::default();
+    let mut by_id = HashMap::new();
+    for track in tracks {
+        let Some(location) = track.location else {
+            continue;
+        };
+        by_id.insert(track.id, library.tracks.len());
+        library.tracks.push(Track {
+            location: location.decode_file_uri().replace('\\', "/"),
+            rating: track.rating.filter(|rating| *rating > 0),
+            ..Track::default()
+        });
+    }
+
+    let mut by_playlist = HashMap::<i32, Vec<usize>>::new();
+    for (playlist_id, track_id) in entries {
+        let (Some(playlist_id), Some(track_id)) = (playlist_id, track_id) else {
+            continue;
+        };
+        if let Some(index) = by_id.get(&track_id) {
+            by_playlist.entry(playlist_id).or_default().push(*index);
+        }
+    }
+    library.playlists = playlists
+        .into_iter()
+        .map(|(id, name)| Playlist {
+            path: vec![name],
+            entries: by_playlist.remove(&id).unwrap_or_default(),
+        })
+        .collect();
+    library
+}
+
+/// Give playlists whose paths join to a name that is already taken a numbered
+/// suffix, as each name becomes its own crate or playlist in Mixxx
+fn rename_duplicates(playlists: &mut [Playlist], separator: &str) {
+    let mut names = HashSet::new();
+    for playlist in playlists {
+        let Some(original) = playlist.path.last().cloned() else {
+            continue;
+        };
+        let mut suffix = 2;
+        while !names.insert(playlist.path.join(separator)) {
+            if let Some(last) = playlist.path.last_mut() {
+                *last = format!("{original} ({suffix})");
+            }
+            suffix += 1;
+        }
+    }
+}
+
+/// Keep the playlists named with `--playlist`, or prompt for them
+fn choose_playlists(
+    args: &Args,
+    available: Vec<Playlist>,
+    force: bool,
+) -> Result<Vec<Playlist>, CustomUserError> {
+    let names: Vec<String> = available
+        .iter()
+        .map(|playlist| playlist.path.join(&args.separator))
+        .collect();
+    let chosen = match (args.playlists.is_empty(), force) {
+        (false, _) => {
+            for name in &args.playlists {
+                if !names.contains(name) {
+                    warn!(r#"Could not find playlist "{name}" in collection! Skipping..."#);
+                }
+            }
+            args.playlists.clone()
+        }
+        (true, true) => names.clone(),
+        (true, false) if names.is_empty() => Vec::new(),
+        (true, false) => {
+            let all: Vec<_> = (0..names.len()).collect();
+            MultiSelect::new("Which playlists would you like to import?", names.clone())
+                .with_default(&all)
+                .prompt()?
+        }
+    };
+    Ok(available
+        .into_iter()
+        .zip(names)
+        .filter(|(_, name)| chosen.contains(name))
+        .map(|(playlist, _)| playlist)
+        .collect())
+}
+
+/// Copy the rating, color, play count and cues of a track, only filling in what is blank
+/// in Mixxx unless told to overwrite
+async fn update_track<C: ConnectionTrait>(
+    db: &C,
+    found: &library_schema::Model,
+    track: &Track,
+    overwrite: bool,
+) -> Result<(), DbErr> {
+    if let Some(rating) = track.rating {
+        if overwrite || found.rating.unwrap_or_default() == 0 {
+            tracks::set_column(db, found.id, library_schema::Column::Rating, rating).await?;
+        }
+    }
+    if let Some(color) = track.color {
+        if overwrite || found.color.is_none() {
+            tracks::set_column(db, found.id, library_schema::Column::Color, color).await?;
+        }
+    }
+    if let Some(play_count) = track.play_count {
+        if overwrite || found.timesplayed.unwrap_or_default() == 0 {
+            tracks::set_column(
+                db,
+                found.id,
+                library_schema::Column::Timesplayed,
+                play_count,
+            )
+            .await?;
+        }
+    }
+    if track.cues.is_empty() {
+        return Ok(());
+    }
+    if cues::has_any(db, found.id).await? {
+        if !overwrite {
+            debug!(r#"Keeping existing cues of track id "{}""#, found.id);
+            return Ok(());
+        }
+        cues::delete_by_track(db, found.id).await?;
+    }
+    let sample_rate = found
+        .samplerate
+        .filter(|rate| *rate > 0)
+        .or(track.sample_rate)
+        .unwrap_or(DEFAULT_SAMPLE_RATE);
+    for cue in &track.cues {
+        let position = to_samples(cue.start, sample_rate);
+        if cue.kind == CueKind::Main {
+            tracks::set_column(db, found.id, library_schema::Column::Cuepoint, position).await?;
+        }
+        cues::create(db, to_cue_model(cue, found.id, position, sample_rate)).await?;
+    }
+    Ok(())
+}
+
+/// Mixxx stores positions as interleaved stereo samples
+#[allow(clippy::cast_possible_truncation)]
+fn to_samples(seconds: f64, sample_rate: i32) -> i32 {
+    (seconds * f64::from(sample_rate)).round() as i32 * 2
+}
+
+fn to_cue_model(
+    cue: &Cue,
+    track_id: i32,
+    position: i32,
+    sample_rate: i32,
+) -> cue_schema::ActiveModel {
+    let length = cue
+        .end
+        .map_or(0, |end| to_samples(end, sample_rate) - position);
+    cue_schema::ActiveModel {
+        id: ActiveValue::NotSet,
+        track_id: ActiveValue::Set(track_id),
+        r#type: ActiveValue::Set(match cue.kind {
+            CueKind::Main => cues::TYPE_MAIN,
+            CueKind::HotCue => cues::TYPE_HOTCUE,
+            CueKind::Loop => cues::TYPE_LOOP,
+        }),
+        position: ActiveValue::Set(position),
+        length: ActiveValue::Set(length.max(0)),
+        hotcue: ActiveValue::Set(cue.hotcue.unwrap_or(-1)),
+        label: ActiveValue::Set(cue.label.clone()),
+        color: cue.color.map_or(ActiveValue::NotSet, ActiveValue::Set),
+    }
+}
+
+async fn import_crate<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+    entries: &[i32],
+) -> Result<(), DbErr> {
+    let crate_id = crates::get_by_name_or_create(db, name).await?;
+    crates::clear_tracks(db, crate_id).await?;
+    for track_id in entries {
+        crates::connect_track(db, crate_id, *track_id).await?;
+    }
+    info!(r#"Imported crate "{name}" with {} tracks"#, entries.len());
+    Ok(())
+}
+
+async fn import_playlist<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+    entries: &[i32],
+) -> Result<(), DbErr> {
+    let found = playlists::get_by_name_or_create(db, name).await?;
+    if found.locked.is_some_and(|locked| locked != 0) {
+        warn!(r#"Playlist "{name}" is locked! Skipping..."#);
+        return Ok(());
+    }
+    playlists::clear_tracks(db, found.id).await?;
+    for (position, track_id) in (1..).zip(entries) {
+        playlists::append_track(db, found.id, *track_id, position, None).await?;
+    }
+    info!(
+        r#"Imported playlist "{name}" with {} tracks"#,
+        entries.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_loop_to_samples() {
+        let cue = Cue {
+            kind: CueKind::Loop,
+            start: 1.0,
+            end: Some(1.5),
+            hotcue: Some(3),
+            label: "Roll".into(),
+            color: None,
+        };
+        let data = to_cue_model(&cue, 7, to_samples(cue.start, 48000), 48000);
+        assert_eq!(data.r#type, ActiveValue::Set(cues::TYPE_LOOP));
+        assert_eq!(data.position, ActiveValue::Set(96000));
+        assert_eq!(data.length, ActiveValue::Set(48000));
+        assert_eq!(data.hotcue, ActiveValue::Set(3));
+        assert_eq!(data.color, ActiveValue::NotSet);
+    }
+
+    #[test]
+    fn converts_cached_library() {
+        let track = |id, location: Option<&str>, rating| CachedTrack {
+            id,
+            location: location.map(Into::into),
+            rating: Some(rating),
+        };
+        let library = from_cache(
+            [
+                track(1, Some("/music/a.mp3"), 3),
+                track(2, None, 0),
+                track(3, Some("/music/b.mp3"), 0),
+            ],
+            [(7, "Friday".into())],
+            [(Some(7), Some(3)), (Some(7), Some(2)), (Some(7), Some(1))],
+        );
+        assert_eq!(library.tracks[0].rating, Some(3));
+        assert_eq!(library.tracks[1].rating, None);
+        assert_eq!(
+            library.playlists,
+            [Playlist {
+                path: vec!["Friday".into()],
+                entries: vec![1, 0],
+            }]
+        );
+    }
+
+    #[test]
+    fn renames_playlists_sharing_a_path() {
+        let playlist = |path: &[&str]| Playlist {
+            path: path.iter().map(ToString::to_string).collect(),
+            entries: Vec::new(),
+        };
+        let mut playlists = [
+            playlist(&["Gigs", "Friday"]),
+            playlist(&["Gigs / Friday"]),
+            playlist(&["Gigs", "Friday"]),
+        ];
+        rename_duplicates(&mut playlists, " / ");
+        let names: Vec<_> = playlists
+            .iter()
+            .map(|playlist| playlist.path.join(" / "))
+            .collect();
+        assert_eq!(
+            names,
+            ["Gigs / Friday", "Gigs / Friday (2)", "Gigs / Friday (3)"]
+        );
+    }
+}
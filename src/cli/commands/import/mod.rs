@@ -1,32 +1,88 @@
 mod directory;
 mod error;
+mod itunes;
+mod library;
 mod playlist;
+mod rhythmbox;
 
+use super::{guard, snapshot::Snapshot};
 use crate::cli::traits::ResolveBase;
 use crate::cli::{traits::NormalizePath, validators};
-use crate::database::functions::crates;
-use crate::database::{disable_fk, enable_fk, get_mixxx_directory, get_sqlite_connection};
-use clap::Parser;
+use crate::database::functions::{crates, playlists};
+use crate::database::{disable_fk, enable_fk, get_mixxx_database_path, get_sqlite_connection};
+use crate::formats;
+use clap::{Parser, Subcommand};
 use error::Error;
 use futures::future::try_join_all;
 use inquire::error::InquireResult;
 use inquire::{CustomUserError, Text};
 use log::{debug, info, trace, warn};
-use sea_orm::{ConnectionTrait, TransactionTrait};
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
 use std::env::current_dir;
 use std::{
     collections::HashMap,
     fs::read_to_string,
     path::{Path, PathBuf},
 };
-use yaml_rust::YamlLoader;
+use yaml_rust::{yaml::Hash, YamlLoader};
 
 #[derive(Parser, Debug, Default)]
+#[command(args_conflicts_with_subcommands = true)]
 pub struct Args {
+    #[command(subcommand)]
+    pub source: Option<Source>,
+    /// Folder containing `mixxxkit.crates.yaml`, or the path to the file itself
     pub path: Option<String>,
+    #[command(flatten)]
+    pub edit: EditArgs,
+}
+
+/// How the installation database is edited, whichever source is imported
+#[derive(Parser, Debug, Default, Clone, Copy)]
+pub struct EditArgs {
+    /// Do not back up your installation database before importing
+    #[arg(long, global = true)]
+    pub no_backup: bool,
+    /// Skip all prompts
+    #[arg(short, long, global = true)]
+    pub force: bool,
+    /// Import even if Mixxx is running
+    #[arg(long, global = true)]
+    pub ignore_running: bool,
+}
+
+/// Collections of other applications to import instead of `mixxxkit.crates.yaml`
+#[derive(Subcommand, Debug)]
+pub enum Source {
+    /// Import a collection exported from Rekordbox as XML
+    Rekordbox(library::Args),
+    /// Import a Traktor collection.nml
+    Traktor(library::Args),
+    /// Import an iTunes Music Library.xml, or the copy of it cached by Mixxx
+    Itunes(itunes::Args),
+    /// Import Rhythmbox's rhythmdb.xml and playlists.xml, or the copy of them cached by Mixxx
+    Rhythmbox(rhythmbox::Args),
+    /// Import the crates of a _Serato_ folder along with the cues and loops stored in your files
+    Serato(library::Args),
 }
 
 pub async fn run(args: &Args) -> Result<(), CustomUserError> {
+    let (library_args, read): (_, library::Reader) = match &args.source {
+        Some(Source::Rekordbox(library_args)) => (library_args, formats::rekordbox::read),
+        Some(Source::Traktor(library_args)) => (library_args, formats::traktor::read),
+        Some(Source::Serato(library_args)) => (library_args, formats::serato::read),
+        Some(Source::Itunes(itunes_args)) => {
+            return itunes::run(itunes_args, args.edit).await;
+        }
+        Some(Source::Rhythmbox(rhythmbox_args)) => {
+            return rhythmbox::run(rhythmbox_args, args.edit).await;
+        }
+        None => return run_mappings(args).await,
+    };
+    library::run(library_args, read, args.edit).await
+}
+
+async fn run_mappings(args: &Args) -> Result<(), CustomUserError> {
     let path_maybe = match &args.path {
         Some(input) => Some(input.to_owned()),
         None => prompt()?,
@@ -36,28 +92,49 @@ pub async fn run(args: &Args) -> Result<(), CustomUserError> {
     };
     trace!("Import command started on {dir}");
 
-    let url: PathBuf = [get_mixxx_directory()?, "mixxxdb.sqlite".into()]
-        .into_iter()
-        .collect();
+    let url = get_mixxx_database_path()?;
 
+    let mappings = get_mappings(&dir)?;
+    let base = dir.resolve_base(current_dir()?);
+    trace!("Mappings acquired!");
+
+    guard::ensure_closed(&url, args.edit.ignore_running).await?;
+    let snapshot = Snapshot::take(&url, args.edit.no_backup).await?;
     let db = &get_sqlite_connection(&url.to_string_lossy()).await?;
+    let result = import(db, mappings, &base).await;
+    snapshot.finish(result)?;
+
+    info!("Successfully imported crates and playlists, {snapshot}");
+    Ok(())
+}
+
+/// Crate and playlist names mapped to the paths they are imported from
+#[derive(Debug, Default)]
+struct Mappings {
+    crates: HashMap<String, Vec<String>>,
+    playlists: HashMap<String, Vec<String>>,
+}
+
+async fn import(
+    db: &DatabaseConnection,
+    mappings: Mappings,
+    base: impl AsRef<Path>,
+) -> Result<(), CustomUserError> {
     disable_fk(db).await?;
     let txn = db.begin().await?;
-
-    let crate_map = get_crate_map(&dir)?;
-    let base = dir.resolve_base(current_dir()?);
-    trace!("Crate map acquired!");
     try_join_all(
-        crate_map
+        mappings
+            .crates
             .into_iter()
-            .map(|(name, paths)| import_paths(&txn, name, &base, paths)),
+            .map(|(name, paths)| import_paths(&txn, name, base.as_ref(), paths)),
     )
     .await?;
-
+    // Playlist positions are computed from the last one, so these cannot run concurrently
+    for (name, paths) in mappings.playlists {
+        import_playlist_paths(&txn, name, paths).await?;
+    }
     txn.commit().await?;
     enable_fk(db).await?;
-
-    info!("Successfully imported crates");
     Ok(())
 }
 
@@ -81,6 +158,33 @@ async fn import_paths<C: ConnectionTrait>(
     Ok(())
 }
 
+async fn import_playlist_paths<C: ConnectionTrait>(
+    db: &C,
+    name: String,
+    paths: Vec<String>,
+) -> Result<(), CustomUserError> {
+    let found = playlists::get_by_name_or_create(db, &name).await?;
+    if found.locked.is_some_and(|locked| locked != 0) {
+        warn!(r#"Playlist "{name}" is locked! Skipping..."#);
+        return Ok(());
+    }
+    trace!(r#"Clearing playlist "{name}""#);
+    playlists::clear_tracks(db, found.id).await?;
+    let mut position = 0;
+    for path in paths {
+        let buf = PathBuf::from(path);
+        if buf.is_dir() {
+            warn!(
+                r#"Only playlist files can be imported as playlists, skipping folder "{}"..."#,
+                buf.to_string_lossy()
+            );
+            continue;
+        }
+        playlist::import_path_as_playlist(db, found.id, buf, &mut position).await?;
+    }
+    Ok(())
+}
+
 fn prompt() -> InquireResult<Option<String>> {
     Text::new("Path to playlists folder:")
         .with_validator(validators::Directory::Required)
@@ -101,7 +205,7 @@ async fn clear_crate<C: ConnectionTrait>(db: &C, id: i32) {
     warn!(r#"Unable to clear tracks from crate [{id}, "{name}"]: {err:?}"#,);
 }
 
-fn get_crate_map<P: AsRef<Path>>(input: P) -> Result<HashMap<String, Vec<String>>, Error> {
+fn get_mappings<P: AsRef<Path>>(input: P) -> Result<Mappings, Error> {
     let buf: PathBuf;
     let path = if input.as_ref().is_dir() {
         buf = input.as_ref().join("mixxxkit.crates.yaml");
@@ -110,34 +214,40 @@ fn get_crate_map<P: AsRef<Path>>(input: P) -> Result<HashMap<String, Vec<String>
         input.as_ref()
     };
     let contents = read_to_string(path)?;
-    parse_crate_map(&contents)
+    parse_mappings(&contents)
 }
 
-fn parse_crate_map(str: &str) -> Result<HashMap<String, Vec<String>>, Error> {
+fn parse_mappings(str: &str) -> Result<Mappings, Error> {
     let Ok(docs) = YamlLoader::load_from_str(str) else {
         return Err(Error::ParsingFailed);
     };
     let doc = &docs[0];
-    let Some(source) = doc["mappings"].as_hash() else {
+    let (crates, playlists) = (doc["mappings"].as_hash(), doc["playlists"].as_hash());
+    if crates.is_none() && playlists.is_none() {
         return Err(Error::ParsingFailed);
-    };
+    }
     let prefix = doc["prefix"].as_str().unwrap_or("");
-    let payload = source
-        .iter()
-        .filter_map(|(key_raw, paths_raw)| {
-            let (Some(key), Some(paths)) = (key_raw.as_str(), paths_raw.as_vec()) else {
-                return None;
-            };
-            let vec = paths
-                .iter()
-                .filter_map(|raw| raw.as_str())
-                .map(ToOwned::to_owned)
-                .collect();
-            let crate_name = prefix.to_owned() + key;
-            Some((crate_name, vec))
-        })
-        .collect();
-    Ok(payload)
+    let collect = |source: Option<&Hash>| {
+        source
+            .into_iter()
+            .flatten()
+            .filter_map(|(key_raw, paths_raw)| {
+                let (Some(key), Some(paths)) = (key_raw.as_str(), paths_raw.as_vec()) else {
+                    return None;
+                };
+                let vec = paths
+                    .iter()
+                    .filter_map(|raw| raw.as_str())
+                    .map(ToOwned::to_owned)
+                    .collect();
+                Some((prefix.to_owned() + key, vec))
+            })
+            .collect()
+    };
+    Ok(Mappings {
+        crates: collect(crates),
+        playlists: collect(playlists),
+    })
 }
 
 #[cfg(test)]
@@ -157,10 +267,26 @@ mod tests {
                     - leek
                     - tomato
         "#};
-        let map = parse_crate_map(str).unwrap();
+        let map = parse_mappings(str).unwrap().crates;
         let vec = map.get("[my] fruit").unwrap();
         assert!(["apple", "tomato"]
             .into_iter()
             .all(|subject| vec.contains(&subject.to_string())));
     }
+
+    #[test]
+    fn parses_playlists_in_order() {
+        let str = indoc! {"
+            playlists:
+                friday:
+                    - sets/friday.m3u8
+                    - sets/encore.m3u8
+        "};
+        let mappings = parse_mappings(str).unwrap();
+        assert!(mappings.crates.is_empty());
+        assert_eq!(
+            mappings.playlists.get("friday").unwrap(),
+            &["sets/friday.m3u8", "sets/encore.m3u8"]
+        );
+    }
 }
@@ -0,0 +1,87 @@
+use super::{split_name, Item};
+use crate::formats::DecodeFileUri;
+
+/// Parse a plain or extended M3U playlist, attaching `#EXTINF` details to the
+/// path that follows them and skipping every other directive
+pub fn parse(contents: &str) -> Vec<Item> {
+    let mut items = Vec::new();
+    let mut pending: Option<Item> = None;
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let line = line.trim();
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            pending = Some(parse_extinf(info));
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        items.push(Item {
+            location: line.decode_file_uri(),
+            ..pending.take().unwrap_or_default()
+        });
+    }
+    items
+}
+
+/// Parse `<duration> [attributes],<artist> - <title>`
+fn parse_extinf(info: &str) -> Item {
+    let mut quoted = false;
+    let split = info.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            quoted = !quoted;
+            None
+        }
+        ',' if !quoted => Some(i),
+        _ => None,
+    });
+    let (head, name) = match split {
+        Some(i) => (&info[..i], info[i + 1..].trim()),
+        None => (info, ""),
+    };
+    let duration = head
+        .split_whitespace()
+        .next()
+        .and_then(|duration| duration.parse::<f64>().ok())
+        .filter(|duration| *duration > 0.0);
+    let (artist, title) = split_name(name);
+    Item {
+        duration,
+        artist,
+        title,
+        ..Item::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_extended_playlist() {
+        let contents = "\u{feff}#EXTM3U\r\n#PLAYLIST:Set\r\n#EXTINF:181,Artist - Song\r\nfile:///music/a%20b.mp3\r\n\r\nrelative/c.flac\r\n";
+        let items = parse(contents);
+        assert_eq!(
+            items,
+            [
+                Item {
+                    location: "/music/a b.mp3".into(),
+                    duration: Some(181.0),
+                    artist: Some("Artist".into()),
+                    title: Some("Song".into()),
+                },
+                Item {
+                    location: "relative/c.flac".into(),
+                    ..Item::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_extinf_attributes() {
+        let item = parse_extinf(r#"-1 tvg-name="a, b",Just A Title"#);
+        assert_eq!(item.duration, None);
+        assert_eq!(item.artist, None);
+        assert_eq!(item.title.as_deref(), Some("Just A Title"));
+    }
+}
@@ -0,0 +1,134 @@
+mod m3u;
+mod pls;
+mod xspf;
+
+use super::error::{Error, Result};
+use crate::cli::traits::{NormalizePath, ResolveBase};
+use crate::database::functions::{crates, playlists, tracks};
+use log::{debug, warn};
+use sea_orm::ConnectionTrait;
+use std::path::{Path, PathBuf};
+
+/// A track listed in a playlist file along with any details the file gives for it
+#[derive(Debug, Default, PartialEq)]
+pub struct Item {
+    pub location: String,
+    pub duration: Option<f64>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+}
+
+pub async fn import_path<C: ConnectionTrait>(db: &C, crate_id: i32, path: PathBuf) -> Result<()> {
+    debug!(
+        r#"Connecting tracks from "{}" to crate id [{crate_id}]"#,
+        path.to_string_lossy()
+    );
+    let items = read_items(&path).await?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    for item in items {
+        let Some((loc, track_id)) = resolve_item(db, &item, base).await else {
+            continue;
+        };
+        debug!(r#"Connecting "{loc}" with track_id "{track_id}" to crate id "{crate_id}""#);
+        let Err(err) = crates::connect_track(db, crate_id, track_id).await else {
+            continue;
+        };
+        warn!(
+            r#"Could not add "{loc}" with track_id "{track_id}" to crate id "{crate_id}": {err:?}"#
+        );
+    }
+    Ok(())
+}
+
+/// Append the tracks of each file in order, keeping duplicates, after the
+/// given position in the playlist
+pub async fn import_path_as_playlist<C: ConnectionTrait>(
+    db: &C,
+    playlist_id: i32,
+    path: PathBuf,
+    position: &mut i32,
+) -> Result<()> {
+    debug!(
+        r#"Appending tracks from "{}" to playlist id [{playlist_id}]"#,
+        path.to_string_lossy()
+    );
+    let items = read_items(&path).await?;
+    let base = path.parent().unwrap_or(Path::new(""));
+    for item in items {
+        let Some((_, track_id)) = resolve_item(db, &item, base).await else {
+            continue;
+        };
+        *position += 1;
+        playlists::append_track(db, playlist_id, track_id, *position, None).await?;
+    }
+    Ok(())
+}
+
+/// Parse a playlist file by its extension, treating unknown ones as M3U
+async fn read_items(path: &Path) -> Result<Vec<Item>> {
+    let bytes = tokio::fs::read(path).await?;
+    let contents = String::from_utf8_lossy(&bytes);
+    let extension = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    match extension.as_deref() {
+        Some("pls") => Ok(pls::parse(&contents)),
+        Some("xspf") => xspf::parse(&contents).map_err(|err| {
+            Error::InvalidPlaylist(path.to_string_lossy().to_string(), err.to_string())
+        }),
+        _ => Ok(m3u::parse(&contents)),
+    }
+}
+
+/// Split a display name in the common `<artist> - <title>` form
+fn split_name(name: &str) -> (Option<String>, Option<String>) {
+    let (artist, title) = match name.split_once(" - ") {
+        Some((artist, title)) => (Some(artist.trim()), title.trim()),
+        None => (None, name.trim()),
+    };
+    (
+        artist.filter(|artist| !artist.is_empty()).map(Into::into),
+        Some(title)
+            .filter(|title| !title.is_empty())
+            .map(Into::into),
+    )
+}
+
+/// Find the track for a playlist item, warning when it is not in the library
+async fn resolve_item<C: ConnectionTrait>(
+    db: &C,
+    item: &Item,
+    base: &Path,
+) -> Option<(String, i32)> {
+    let loc = PathBuf::from(&item.location)
+        .resolve_base(base)
+        .normalize_path();
+    match find_track(db, &loc, item).await {
+        Ok(Some(track_id)) => Some((loc, track_id)),
+        Ok(None) => {
+            let source = format!(r#"Could not find "{loc}" in database!"#);
+            let tip = "Try rescanning your library and checking for case sensitivity.";
+            warn!("{source} {tip}");
+            None
+        }
+        Err(err) => {
+            warn!(r#"Could not retrieve "{loc}" from database: {err:?}"#);
+            None
+        }
+    }
+}
+
+/// Match by path first, then by the artist and title given in the playlist
+async fn find_track<C: ConnectionTrait>(db: &C, loc: &str, item: &Item) -> Result<Option<i32>> {
+    if let Some(track) = tracks::get_by_location(db, loc).await? {
+        return Ok(Some(track.id));
+    }
+    let Some(title) = &item.title else {
+        return Ok(None);
+    };
+    let found = tracks::find_by_tags(db, item.artist.as_deref(), title, item.duration).await?;
+    if let Some(track_id) = found {
+        debug!(r#"Matched "{loc}" by its tags to track_id "{track_id}""#);
+    }
+    Ok(found)
+}
@@ -0,0 +1,70 @@
+use super::{split_name, Item};
+use crate::formats::DecodeFileUri;
+use std::collections::BTreeMap;
+
+/// Parse a PLS playlist, ordering entries by the number in their `FileN` keys
+pub fn parse(contents: &str) -> Vec<Item> {
+    let mut entries = BTreeMap::<u32, Item>::new();
+    for line in contents.trim_start_matches('\u{feff}').lines() {
+        let Some((key, value)) = line.trim().split_once('=') else {
+            continue;
+        };
+        let key = key.trim().to_lowercase();
+        let value = value.trim();
+        let Some((field, index)) = ["file", "title", "length"].into_iter().find_map(|field| {
+            let index = key.strip_prefix(field)?.parse::<u32>().ok()?;
+            Some((field, index))
+        }) else {
+            continue;
+        };
+        let entry = entries.entry(index).or_default();
+        match field {
+            "file" => entry.location = value.decode_file_uri(),
+            "title" => (entry.artist, entry.title) = split_name(value),
+            _ => {
+                entry.duration = value.parse::<f64>().ok().filter(|duration| *duration > 0.0);
+            }
+        }
+    }
+    entries
+        .into_values()
+        .filter(|entry| !entry.location.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parses_numbered_entries() {
+        let contents = indoc! {"
+            [playlist]
+            File2=file:///music/b%20c.mp3
+            Title2=Artist - Song
+            Length2=-1
+            File1=../a.flac
+            Length1=200
+            Title3=Orphaned
+            NumberOfEntries=2
+            Version=2
+        "};
+        assert_eq!(
+            parse(contents),
+            [
+                Item {
+                    location: "../a.flac".into(),
+                    duration: Some(200.0),
+                    ..Item::default()
+                },
+                Item {
+                    location: "/music/b c.mp3".into(),
+                    artist: Some("Artist".into()),
+                    title: Some("Song".into()),
+                    ..Item::default()
+                },
+            ]
+        );
+    }
+}
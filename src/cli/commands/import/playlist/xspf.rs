@@ -0,0 +1,86 @@
+use super::Item;
+use crate::formats::{percent_decode, DecodeFileUri};
+use roxmltree::{Document, Node};
+
+/// Parse an XSPF playlist, taking the first `<location>` of each track and
+/// keeping `<title>` and `<creator>` for matching by tags
+pub fn parse(contents: &str) -> Result<Vec<Item>, roxmltree::Error> {
+    let doc = Document::parse(contents.trim_start_matches('\u{feff}'))?;
+    let items = doc
+        .descendants()
+        .filter(|node| node.has_tag_name("trackList"))
+        .flat_map(|list| list.children().filter(|node| node.has_tag_name("track")))
+        .filter_map(|track| {
+            let location = get_text(track, "location")?;
+            Some(Item {
+                location: match location.contains("://") {
+                    true => location.decode_file_uri(),
+                    false => percent_decode(location),
+                },
+                // Durations are given in milliseconds
+                duration: get_text(track, "duration")
+                    .and_then(|duration| duration.parse::<f64>().ok())
+                    .map(|duration| duration / 1000.0)
+                    .filter(|duration| *duration > 0.0),
+                artist: get_text(track, "creator").map(Into::into),
+                title: get_text(track, "title").map(Into::into),
+            })
+        })
+        .collect();
+    Ok(items)
+}
+
+fn get_text<'a>(track: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    track
+        .children()
+        .find(|node| node.has_tag_name(name))
+        .and_then(|node| node.text())
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    #[test]
+    fn parses_tracks() {
+        let contents = indoc! {r#"
+            <?xml version="1.0" encoding="UTF-8"?>
+            <playlist version="1" xmlns="http://xspf.org/ns/0/">
+              <title>Set</title>
+              <trackList>
+                <track>
+                  <location>file:///music/Caf%C3%A9.mp3</location>
+                  <location>https://example.com/mirror.mp3</location>
+                  <creator>Artist</creator>
+                  <title>Song</title>
+                  <duration>181000</duration>
+                </track>
+                <track>
+                  <location>sub/a%20b.flac</location>
+                </track>
+                <track>
+                  <title>No Location</title>
+                </track>
+              </trackList>
+            </playlist>
+        "#};
+        assert_eq!(
+            parse(contents).unwrap(),
+            [
+                Item {
+                    location: "/music/Café.mp3".into(),
+                    duration: Some(181.0),
+                    artist: Some("Artist".into()),
+                    title: Some("Song".into()),
+                },
+                Item {
+                    location: "sub/a b.flac".into(),
+                    ..Item::default()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use super::{guard, snapshot::Snapshot};
+use crate::database::codec::{Keys, KEY_MAP};
+use crate::database::functions::{crates, tracks};
+use crate::database::key::{self, Key};
+use crate::database::schema::library;
+use crate::database::{get_mixxx_database_path, get_sqlite_connection};
+use clap::{Parser, ValueEnum};
+use inquire::{CustomUserError, Select};
+use log::{debug, info, warn};
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbErr, TransactionTrait};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+#[derive(Parser, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct Args {
+    /// Notation to rewrite keys in. If omitted, you will be prompted.
+    #[arg(long, value_enum)]
+    pub notation: Option<Notation>,
+    /// Only rewrite the keys of tracks in these crates
+    #[arg(long = "crate")]
+    pub crates: Vec<String>,
+    /// Print what would change without saving anything
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Do not back up your installation database before editing it
+    #[arg(long)]
+    pub no_backup: bool,
+    /// Skip all prompts
+    #[arg(short, long)]
+    pub force: bool,
+    /// Edit the database even if Mixxx is running
+    #[arg(long)]
+    pub ignore_running: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Display, EnumIter)]
+pub enum Notation {
+    /// Also known as Camelot, from 1A to 12B
+    #[default]
+    #[strum(to_string = "Lancelot, such as 8A and 8B")]
+    Lancelot,
+    #[strum(to_string = "OpenKey, such as 1m and 1d")]
+    OpenKey,
+    #[strum(to_string = "Traditional, such as Am and C")]
+    Traditional,
+}
+
+impl From<Notation> for key::Notation {
+    fn from(notation: Notation) -> Self {
+        match notation {
+            Notation::Lancelot => key::Notation::Lancelot,
+            Notation::OpenKey => key::Notation::OpenKey,
+            Notation::Traditional => key::Notation::Traditional,
+        }
+    }
+}
+
+pub async fn run(args: &Args) -> Result<(), CustomUserError> {
+    let notation = match (args.notation, args.force) {
+        (Some(notation), _) => notation,
+        (None, false) => Select::new(
+            "Which notation should keys be written in?",
+            Notation::iter().collect(),
+        )
+        .prompt()?,
+        (None, true) => Notation::default(),
+    };
+
+    let url = get_mixxx_database_path()?;
+    let snapshot = match args.dry_run {
+        true => Snapshot::none(),
+        false => {
+            guard::ensure_closed(&url, args.ignore_running).await?;
+            Snapshot::take(&url, args.no_backup).await?
+        }
+    };
+    let db = &get_sqlite_connection(&url.to_string_lossy()).await?;
+    let result = rewrite(db, args, notation.into()).await;
+    let rewritten = snapshot.finish(result)?;
+
+    match args.dry_run {
+        true => info!("Dry run finished, {rewritten} keys would be rewritten"),
+        false => info!("Successfully rewrote {rewritten} keys, {snapshot}"),
+    }
+    Ok(())
+}
+
+async fn rewrite(
+    db: &DatabaseConnection,
+    args: &Args,
+    notation: key::Notation,
+) -> Result<usize, CustomUserError> {
+    let txn = db.begin().await?;
+    let in_crates = crates::get_track_ids_by_names(&txn, &args.crates).await?;
+    let (mut rewritten, mut unknown) = (0, 0);
+    for track in tracks::get(&txn).await? {
+        if track.mixxx_deleted.unwrap_or_default() != 0
+            || in_crates
+                .as_ref()
+                .is_some_and(|ids| !ids.contains(&track.id))
+        {
+            continue;
+        }
+        let text = track.key.as_deref().unwrap_or_default();
+        // The text is what taggers and users edit, so it wins over the id
+        let Some(key) = Key::parse(text).or_else(|| Key::from_id(track.key_id?)) else {
+            if !text.is_empty() {
+                debug!(r#"Could not read key "{text}" of track id "{}""#, track.id);
+                unknown += 1;
+            }
+            continue;
+        };
+        let formatted = key.format(notation);
+        if text == formatted && track.key_id == Some(key.id()) {
+            continue;
+        }
+        debug!(
+            r#"Rewriting key "{text}" of track id "{}" as "{formatted}""#,
+            track.id
+        );
+        if !args.dry_run {
+            save(&txn, &track, key, &formatted).await?;
+        }
+        rewritten += 1;
+    }
+    txn.commit().await?;
+    if unknown > 0 {
+        warn!("Could not read the keys of {unknown} tracks! Run with --debug to list them.");
+    }
+    Ok(rewritten)
+}
+
+async fn save<C: ConnectionTrait>(
+    db: &C,
+    track: &library::Model,
+    key: Key,
+    formatted: &str,
+) -> Result<(), DbErr> {
+    tracks::set_column(db, track.id, library::Column::Key, formatted).await?;
+    tracks::set_column(db, track.id, library::Column::KeyId, key.id()).await?;
+    let keys = match (&track.keys_version, &track.keys) {
+        (Some(version), Some(data)) => Keys::decode(version, data),
+        _ => None,
+    };
+    if let Some(keys) = keys {
+        let data = update_keys(keys, key, formatted).encode();
+        tracks::set_column(db, track.id, library::Column::Keys, data).await?;
+        tracks::set_column(db, track.id, library::Column::KeysVersion, KEY_MAP).await?;
+    }
+    Ok(())
+}
+
+/// Mixxx reads the key of a track from its key map rather than the `key`
+/// column, so the global key has to agree with it
+fn update_keys(mut keys: Keys, key: Key, formatted: &str) -> Keys {
+    keys.global_key = key.id();
+    if keys.global_key_text.is_some() {
+        keys.global_key_text = Some(formatted.to_owned());
+    }
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::codec::{KeyChange, Source};
+
+    #[test]
+    fn keeps_key_changes_when_updating_key_map() {
+        let keys = Keys {
+            global_key: 0,
+            changes: vec![KeyChange { key: 22, frame: 0 }],
+            source: Source::FileMetadata,
+            global_key_text: Some("A minor".into()),
+        };
+        let key = Key::parse("A minor").unwrap();
+        assert_eq!(
+            update_keys(keys, key, "8A"),
+            Keys {
+                global_key: 22,
+                changes: vec![KeyChange { key: 22, frame: 0 }],
+                source: Source::FileMetadata,
+                global_key_text: Some("8A".into()),
+            }
+        );
+    }
+}
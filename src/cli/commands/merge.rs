@@ -1,17 +1,23 @@
+use super::{guard, snapshot::Snapshot};
 use crate::cli::{traits::NormalizePath, validators};
 use crate::database::get_mixxx_database_path;
 use crate::database::{
-    disable_fk, enable_fk, functions, get_sqlite_connection, schema::directories,
+    disable_fk, enable_fk,
+    functions::{self, report::Report},
+    get_sqlite_connection,
+    schema::directories,
 };
 use crate::error::MixxxkitExit;
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use inquire::validator::StringValidator;
-use inquire::{Confirm, CustomUserError, Text};
+use inquire::{Confirm, CustomUserError, Select, Text};
 use log::{debug, info};
-use sea_orm::TransactionTrait;
+use sea_orm::{DatabaseConnection, TransactionTrait};
 use std::{collections::HashMap, fs::copy};
+use strum::{Display, EnumIter, IntoEnumIterator};
 
 #[derive(Parser, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Args {
     /// Source database to pull tracks from. If omitted, you will be prompted for paths.
     pub source: Option<String>,
@@ -19,9 +25,30 @@ pub struct Args {
     pub target: Option<String>,
     /// Output database as new file to this location. If omitted, target is edited in place.
     pub output: Option<String>,
+    /// How to resolve tracks whose file already exists in the target. If omitted, you will be prompted.
+    #[arg(long, value_enum)]
+    pub track_conflict: Option<TrackConflict>,
+    /// How to merge crates whose name already exists in the target. If omitted, you will be prompted.
+    #[arg(long, value_enum)]
+    pub crate_conflict: Option<NameConflict>,
+    /// How to merge playlists whose name already exists in the target. If omitted, you will be prompted.
+    #[arg(long, value_enum)]
+    pub playlist_conflict: Option<NameConflict>,
+    /// How to handle the Auto DJ queue and set log history. If omitted, you will be prompted.
+    #[arg(long, value_enum)]
+    pub hidden_playlists: Option<HiddenPlaylists>,
+    /// Run the merge without saving any changes and print a report of what would change
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Do not back up the database before editing it in place
+    #[arg(long)]
+    pub no_backup: bool,
     /// Skip all prompts and force execution
     #[arg(short, long)]
     pub force: bool,
+    /// Edit the database even if Mixxx is running
+    #[arg(long)]
+    pub ignore_running: bool,
 }
 
 pub async fn run(args: &Args) -> Result<(), CustomUserError> {
@@ -29,39 +56,229 @@ pub async fn run(args: &Args) -> Result<(), CustomUserError> {
 
     let source_db = get_sqlite_connection(&paths.source).await?;
     let dirs = functions::directories::get(&source_db).await?;
-    let dir_map = match args.force {
-        false => Some(prompt_for_directories(&dirs)),
-        true => None,
-    };
+    let policies = prompt_for_policies(args, &dirs)?;
 
-    let output_path = match (paths.target, paths.output) {
+    let (output_path, in_place) = match (paths.target, paths.output) {
+        (Some(target), _) if args.dry_run => (target, true),
         (Some(target), Some(output)) => {
-            if target != output {
+            let in_place = target == output;
+            if !in_place {
                 copy(target, &output)?;
             }
-            output
+            (output, in_place)
         }
-        (Some(target), None) => target,
-        _ => get_mixxx_database_path()?.to_string_lossy().to_string(),
+        (Some(target), None) => (target, true),
+        _ => (
+            get_mixxx_database_path()?.to_string_lossy().to_string(),
+            true,
+        ),
+    };
+    let snapshot = match in_place && !args.dry_run {
+        true => {
+            guard::ensure_closed(&output_path, args.ignore_running).await?;
+            Snapshot::take(&output_path, args.no_backup).await?
+        }
+        false => Snapshot::none(),
     };
     let output_db = &get_sqlite_connection(&output_path).await?;
 
+    let result = Box::pin(merge(&source_db, output_db, &dirs, &policies, args.dry_run)).await;
+    let report = snapshot.finish(result)?;
+
+    match args.dry_run {
+        true => info!("Dry run finished, no changes were saved:\n{report}"),
+        false => info!(
+            "Successfully merged libraries, {snapshot}:\n{}",
+            report.summary()
+        ),
+    }
+    Ok(())
+}
+
+/// How to resolve a source track whose file already exists in the target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Display, EnumIter)]
+pub enum TrackConflict {
+    /// Leave the target track untouched
+    #[default]
+    #[strum(to_string = "Keep the target track")]
+    KeepTarget,
+    /// Overwrite the target track with the source track
+    #[strum(to_string = "Take the source track")]
+    TakeSource,
+    /// Keep whichever track was most recently synchronized with its file
+    #[strum(to_string = "Take the most recently synchronized track")]
+    Newest,
+    /// Fill in fields that are empty in the target track from the source track
+    #[strum(to_string = "Fill in empty fields of the target track")]
+    FillNulls,
+}
+
+impl From<TrackConflict> for functions::tracks::TrackConflict {
+    fn from(conflict: TrackConflict) -> Self {
+        match conflict {
+            TrackConflict::KeepTarget => functions::tracks::TrackConflict::KeepTarget,
+            TrackConflict::TakeSource => functions::tracks::TrackConflict::TakeSource,
+            TrackConflict::Newest => functions::tracks::TrackConflict::Newest,
+            TrackConflict::FillNulls => functions::tracks::TrackConflict::FillNulls,
+        }
+    }
+}
+
+/// How to handle an incoming crate or playlist whose name is already taken in the target
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Display, EnumIter)]
+pub enum NameConflict {
+    /// Combine the incoming tracks into the existing item
+    #[default]
+    #[strum(to_string = "Union into the existing item")]
+    Union,
+    /// Create a new item with a numbered suffix
+    #[strum(to_string = "Rename with a numbered suffix")]
+    Rename,
+    /// Leave the existing item alone and drop the incoming one
+    #[strum(to_string = "Skip the incoming item")]
+    Skip,
+}
+
+impl From<NameConflict> for functions::NameConflict {
+    fn from(conflict: NameConflict) -> Self {
+        match conflict {
+            NameConflict::Union => functions::NameConflict::Union,
+            NameConflict::Rename => functions::NameConflict::Rename,
+            NameConflict::Skip => functions::NameConflict::Skip,
+        }
+    }
+}
+
+/// How to handle Mixxx's special hidden playlists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum, Display, EnumIter)]
+pub enum HiddenPlaylists {
+    /// Leave out the Auto DJ queue and set log history
+    #[default]
+    #[strum(to_string = "Skip the Auto DJ queue and history")]
+    Skip,
+    /// Append the Auto DJ queue to the target queue and copy over set log history
+    #[strum(to_string = "Append the Auto DJ queue and copy history")]
+    Merge,
+}
+
+impl From<HiddenPlaylists> for functions::playlists::HiddenPlaylists {
+    fn from(hidden: HiddenPlaylists) -> Self {
+        match hidden {
+            HiddenPlaylists::Skip => functions::playlists::HiddenPlaylists::Skip,
+            HiddenPlaylists::Merge => functions::playlists::HiddenPlaylists::Merge,
+        }
+    }
+}
+
+struct Policies {
+    dir_map: Option<HashMap<String, String>>,
+    tracks: TrackConflict,
+    crates: NameConflict,
+    playlists: NameConflict,
+    hidden: HiddenPlaylists,
+}
+
+async fn merge(
+    source_db: &DatabaseConnection,
+    output_db: &DatabaseConnection,
+    dirs: &[directories::Model],
+    policies: &Policies,
+    dry_run: bool,
+) -> Result<Report, CustomUserError> {
+    let dir_map = policies.dir_map.as_ref();
     disable_fk(output_db).await?;
     let txn = output_db.begin().await?;
+    let mut report = Report::default();
+
+    functions::directories::insert(&txn, dirs, dir_map, &mut report).await?;
 
-    functions::directories::insert(&txn, &dirs, dir_map.as_ref()).await?;
+    let locs = functions::locations::get(source_db).await?;
+    let loc_map = functions::locations::insert(&txn, locs, dir_map, &mut report).await?;
 
-    let locs = functions::locations::get(&source_db).await?;
-    let loc_map = functions::locations::insert(&txn, locs, dir_map.as_ref()).await?;
+    let tracks = functions::tracks::get(source_db).await?;
+    let track_map =
+        functions::tracks::insert(&txn, tracks, &loc_map, policies.tracks.into(), &mut report)
+            .await?;
 
-    let tracks = functions::tracks::get(&source_db).await?;
-    functions::tracks::insert(&txn, tracks, &loc_map).await?;
+    let cues = functions::cues::get(source_db).await?;
+    functions::cues::insert(&txn, cues, &track_map, &mut report).await?;
 
-    txn.commit().await?;
+    let crates = functions::crates::get(source_db).await?;
+    let crate_tracks = functions::crates::get_tracks(source_db).await?;
+    functions::crates::insert(
+        &txn,
+        crates,
+        crate_tracks,
+        &track_map,
+        policies.crates.into(),
+        &mut report,
+    )
+    .await?;
+
+    let playlists = functions::playlists::get(source_db).await?;
+    let playlist_tracks = functions::playlists::get_tracks(source_db).await?;
+    functions::playlists::insert(
+        &txn,
+        playlists,
+        playlist_tracks,
+        &track_map,
+        policies.playlists.into(),
+        policies.hidden.into(),
+        &mut report,
+    )
+    .await?;
+
+    match dry_run {
+        true => txn.rollback().await?,
+        false => txn.commit().await?,
+    }
     enable_fk(output_db).await?;
+    Ok(report)
+}
 
-    info!("Successfully merged libraries");
-    Ok(())
+fn prompt_for_policies(
+    args: &Args,
+    dirs: &[directories::Model],
+) -> Result<Policies, CustomUserError> {
+    let dir_map = match args.force {
+        false => Some(prompt_for_directories(dirs)),
+        true => None,
+    };
+    let tracks = match (args.track_conflict, args.force) {
+        (Some(conflict), _) => conflict,
+        (None, false) => Select::new(
+            "How should tracks that already exist in the target be merged?",
+            TrackConflict::iter().collect(),
+        )
+        .prompt()?,
+        (None, true) => TrackConflict::default(),
+    };
+    let crates = match (args.crate_conflict, args.force) {
+        (Some(conflict), _) => conflict,
+        (None, false) => prompt_for_conflict("How should crates with the same name be merged?")?,
+        (None, true) => NameConflict::default(),
+    };
+    let playlists = match (args.playlist_conflict, args.force) {
+        (Some(conflict), _) => conflict,
+        (None, false) => prompt_for_conflict("How should playlists with the same name be merged?")?,
+        (None, true) => NameConflict::default(),
+    };
+    let hidden = match (args.hidden_playlists, args.force) {
+        (Some(hidden), _) => hidden,
+        (None, false) => Select::new(
+            "How should the Auto DJ queue and history be merged?",
+            HiddenPlaylists::iter().collect(),
+        )
+        .prompt()?,
+        (None, true) => HiddenPlaylists::default(),
+    };
+    Ok(Policies {
+        dir_map,
+        tracks,
+        crates,
+        playlists,
+        hidden,
+    })
 }
 
 struct DatabasePaths {
@@ -85,11 +302,14 @@ fn prompt_for_databases(args: &Args) -> Result<DatabasePaths, CustomUserError> {
     };
 
     if args.source.is_some() && args.target.is_none() {
-        return prompt_for_confirmation(DatabasePaths {
-            source,
-            target: None,
-            output: None,
-        });
+        return prompt_for_confirmation(
+            args,
+            DatabasePaths {
+                source,
+                target: None,
+                output: None,
+            },
+        );
     }
 
     let target_raw = Text::new("Path to target database:")
@@ -122,15 +342,21 @@ fn prompt_for_databases(args: &Args) -> Result<DatabasePaths, CustomUserError> {
         output,
     };
     match payload.target.is_none() && payload.output.is_none() {
-        true => prompt_for_confirmation(payload),
+        true => prompt_for_confirmation(args, payload),
         false => Ok(payload),
     }
 }
 
-fn prompt_for_confirmation(paths: DatabasePaths) -> Result<DatabasePaths, CustomUserError> {
+fn prompt_for_confirmation(
+    args: &Args,
+    paths: DatabasePaths,
+) -> Result<DatabasePaths, CustomUserError> {
+    if args.dry_run {
+        return Ok(paths);
+    }
     let check =
         Confirm::new("You are going to edit your Mixxx database in-place. Are you sure? (y/n)")
-            .with_help_message("Please make a backup of your database before continuing!")
+            .with_help_message("A backup will be taken first unless you pass --no-backup")
             .prompt_skippable()
             .unwrap();
     match check.is_some_and(|b| b) {
@@ -138,6 +364,11 @@ fn prompt_for_confirmation(paths: DatabasePaths) -> Result<DatabasePaths, Custom
         false => Err(Box::new(MixxxkitExit::Abort)),
     }
 }
+
+fn prompt_for_conflict(message: &str) -> Result<NameConflict, CustomUserError> {
+    Ok(Select::new(message, NameConflict::iter().collect()).prompt()?)
+}
+
 fn prompt_for_directories(dirs: &[directories::Model]) -> HashMap<String, String> {
     let mut map = HashMap::<String, String>::with_capacity(dirs.len());
     for dir in dirs {
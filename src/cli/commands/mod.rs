@@ -1,6 +1,12 @@
 mod backup;
+mod beats;
+mod export;
+mod guard;
 mod import;
+mod keys;
 mod merge;
+mod restore;
+mod snapshot;
 
 use clap::Subcommand;
 use inquire::CustomUserError;
@@ -10,21 +16,37 @@ use strum::{Display, EnumIter};
 pub enum Command {
     /// Create a backup of your installation database
     #[command()]
-    Backup,
-    /// Import m3u8 files as crates into your library
+    Backup(backup::Args),
+    /// Shift, halve, double, snap or lock the beat grids of many tracks at once
+    #[command()]
+    Beats(beats::Args),
+    /// Export crates as m3u8 playlists, or your library to other applications
+    #[command()]
+    Export(export::Args),
+    /// Import playlists and collections of other applications into your library
     #[command()]
     Import(import::Args),
+    /// Rewrite the keys of your tracks in Lancelot, Open Key or traditional notation
+    #[command()]
+    Keys(keys::Args),
     /// Merge two libraries together
     #[command()]
     Merge(merge::Args),
+    /// Restore your installation database from a backup
+    #[command()]
+    Restore(restore::Args),
 }
 
 impl Command {
     pub async fn run(&self) -> Result<(), CustomUserError> {
         match self {
-            Command::Backup => backup::run(),
+            Command::Backup(args) => backup::run(args).await,
+            Command::Beats(args) => beats::run(args).await,
+            Command::Export(args) => export::run(args).await,
             Command::Import(args) => import::run(args).await,
+            Command::Keys(args) => keys::run(args).await,
             Command::Merge(args) => merge::run(args).await,
+            Command::Restore(args) => restore::run(args).await,
         }
     }
 }
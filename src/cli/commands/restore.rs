@@ -0,0 +1,120 @@
+use super::guard;
+use crate::database::backup::{self, Backup, Stats};
+use crate::database::get_mixxx_database_path;
+use crate::error::MixxxkitExit;
+use clap::Parser;
+use inquire::{Confirm, CustomUserError, Select};
+use log::{error, info, warn};
+use std::fmt::{self, Display, Formatter};
+use std::fs::{copy, remove_file};
+use std::io;
+use std::path::Path;
+
+#[derive(Parser, Debug, Default)]
+pub struct Args {
+    /// File name of the backup to restore. If omitted, you will be prompted to pick one.
+    pub name: Option<String>,
+    /// Skip all prompts and force execution
+    #[arg(short, long)]
+    pub force: bool,
+    /// Edit the database even if Mixxx is running
+    #[arg(long)]
+    pub ignore_running: bool,
+}
+
+pub async fn run(args: &Args) -> Result<(), CustomUserError> {
+    let database = get_mixxx_database_path()?;
+    let backups = backup::list(&database)?;
+    if backups.is_empty() {
+        error!(
+            r#"Could not find any backups in "{}""#,
+            backup::get_backups_directory(&database).to_string_lossy()
+        );
+        return Err(Box::new(MixxxkitExit::Abort));
+    }
+
+    let chosen = match &args.name {
+        Some(name) => {
+            let Some(found) = backups.into_iter().find(|backup| &backup.name() == name) else {
+                error!(r#"Could not find backup "{name}""#);
+                return Err(Box::new(MixxxkitExit::Abort));
+            };
+            found
+        }
+        None => prompt_for_backup(backups).await?,
+    };
+
+    guard::ensure_closed(&database, args.ignore_running).await?;
+    if !args.force {
+        let check = Confirm::new(&format!(
+            r#"Your Mixxx database will be replaced with "{}". Are you sure? (y/n)"#,
+            chosen.name()
+        ))
+        .with_help_message("A backup of your current database will be taken first")
+        .prompt_skippable()?;
+        if !check.is_some_and(|b| b) {
+            return Err(Box::new(MixxxkitExit::Abort));
+        }
+    }
+
+    if database.exists() {
+        let safety = backup::create(&database).await?;
+        info!(
+            r#"Backed up current database to "{}""#,
+            safety.to_string_lossy()
+        );
+    }
+    copy(&chosen.path, &database)?;
+    remove_side_files(&database)?;
+
+    info!(r#"Successfully restored "{}""#, chosen.name());
+    Ok(())
+}
+
+/// Leftover `-wal`, `-shm` or `-journal` files belong to the replaced database
+/// and would otherwise be replayed on top of the restored one
+fn remove_side_files(database: &Path) -> io::Result<()> {
+    for suffix in ["-wal", "-shm", "-journal"] {
+        let mut name = database.as_os_str().to_owned();
+        name.push(suffix);
+        let path = Path::new(&name);
+        if path.exists() {
+            remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+struct BackupOption {
+    backup: Backup,
+    stats: Option<Stats>,
+}
+
+impl Display for BackupOption {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.stats {
+            Some(stats) => write!(
+                f,
+                "{} - {} tracks, {} crates",
+                self.backup, stats.tracks, stats.crates
+            ),
+            None => write!(f, "{} - unreadable", self.backup),
+        }
+    }
+}
+
+async fn prompt_for_backup(backups: Vec<Backup>) -> Result<Backup, CustomUserError> {
+    let mut options = Vec::with_capacity(backups.len());
+    for backup in backups {
+        let stats = match backup::get_stats(&backup.path).await {
+            Ok(stats) => Some(stats),
+            Err(err) => {
+                warn!(r#"Could not read "{}": {err:?}"#, backup.name());
+                None
+            }
+        };
+        options.push(BackupOption { backup, stats });
+    }
+    let chosen = Select::new("Which backup would you like to restore?", options).prompt()?;
+    Ok(chosen.backup)
+}
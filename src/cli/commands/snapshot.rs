@@ -0,0 +1,53 @@
+use crate::database::backup;
+use inquire::CustomUserError;
+use log::{error, info, warn};
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Backup taken before a command edits a database in place
+pub struct Snapshot(Option<PathBuf>);
+
+impl Snapshot {
+    /// No backup is needed because nothing is edited in place
+    pub fn none() -> Self {
+        Self(None)
+    }
+
+    pub async fn take(database: impl AsRef<Path>, skip: bool) -> Result<Self, CustomUserError> {
+        let database = database.as_ref();
+        if skip {
+            warn!(
+                r#"Editing "{}" without taking a backup first"#,
+                database.to_string_lossy()
+            );
+            return Ok(Self(None));
+        }
+        let path = backup::create(database).await?;
+        info!(
+            r#"Backed up "{}" to "{}""#,
+            database.to_string_lossy(),
+            path.to_string_lossy()
+        );
+        Ok(Self(Some(path)))
+    }
+
+    /// Point to the backup when a command fails so it can be rolled back
+    pub fn finish<T>(&self, result: Result<T, CustomUserError>) -> Result<T, CustomUserError> {
+        if let (Err(_), Some(path)) = (&result, &self.0) {
+            error!(
+                r#"Command failed! Your database was backed up beforehand to "{}""#,
+                path.to_string_lossy()
+            );
+        }
+        result
+    }
+}
+
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(path) => write!(f, r#"backup saved to "{}""#, path.to_string_lossy()),
+            None => write!(f, "no backup taken"),
+        }
+    }
+}
@@ -4,6 +4,7 @@ mod validators;
 
 use clap::Parser;
 use commands::Command;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(author, version, about)]
@@ -13,6 +14,10 @@ pub struct Cli {
     #[allow(clippy::option_option)]
     pub debug: Option<Option<String>>,
 
+    /// Mixxx settings directory containing `mixxxdb.sqlite`. If omitted, the usual locations are searched.
+    #[arg(long, global = true, env = "MIXXXKIT_SETTINGS_PATH", value_names = ["path"])]
+    pub settings_path: Option<PathBuf>,
+
     #[command(subcommand)]
     pub command: Option<Command>,
 }
@@ -6,7 +6,7 @@ pub trait NormalizePath {
     fn normalize_path(self) -> String;
 }
 
-impl<'a> NormalizePath for &'a str {
+impl NormalizePath for &str {
     fn normalize_path(self) -> String {
         self.trim().trim_surround('"').replace('\\', "/")
     }
@@ -1,8 +1,8 @@
 use crate::cli::traits::NormalizePath;
-use crate::database::get_sqlite_connection;
+use crate::database::{check_integrity, get_sqlite_connection};
 use inquire::validator::{StringValidator, Validation};
 use inquire::CustomUserError;
-use sea_orm::{ConnectionTrait, DatabaseBackend, DbErr, Statement};
+use sea_orm::DbErr;
 use std::path::Path;
 use tokio::{runtime, task};
 
@@ -20,12 +20,10 @@ impl Database {
 
 async fn can_open_database(path: &str) -> Result<(), DbErr> {
     let db = get_sqlite_connection(path).await?;
-    db.execute(Statement::from_string(
-        DatabaseBackend::Sqlite,
-        "PRAGMA integrity_check",
-    ))
-    .await?;
-    Ok(())
+    match check_integrity(&db).await? {
+        None => Ok(()),
+        Some(message) => Err(DbErr::Custom(message)),
+    }
 }
 
 impl StringValidator for Database {
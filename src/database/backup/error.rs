@@ -0,0 +1,11 @@
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Database ran into error {0:?}")]
+    Db(#[from] sea_orm::DbErr),
+    #[error("Could not access backups {0:?}")]
+    Io(#[from] std::io::Error),
+    #[error("Backup failed integrity check: {0}")]
+    Integrity(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
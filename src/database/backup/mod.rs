@@ -0,0 +1,203 @@
+mod error;
+pub mod retention;
+
+use super::schema::{crates, library};
+use super::{check_integrity, get_sqlite_connection};
+use chrono::{DateTime, Local, TimeDelta, TimeZone, Utc};
+use log::debug;
+use sea_orm::{
+    ColumnTrait, ConnectionTrait, DatabaseBackend, EntityTrait, PaginatorTrait, QueryFilter,
+    Statement,
+};
+use std::fmt::{self, Display, Formatter};
+use std::fs::{create_dir_all, read_dir, remove_file};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub use error::{Error, Result};
+
+/// A backup file found next to a database
+#[derive(Debug, Clone)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub created: DateTime<Utc>,
+    pub size: u64,
+}
+
+impl Backup {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+/// Number of tracks and crates stored in a backup
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+    pub tracks: u64,
+    pub crates: u64,
+}
+
+/// Backups are kept in a `backups` folder next to the database they were taken from
+pub fn get_backups_directory(database: impl AsRef<Path>) -> PathBuf {
+    database
+        .as_ref()
+        .parent()
+        .unwrap_or_else(|| Path::new(""))
+        .join("backups")
+}
+
+/// Write a consistent copy of a database into its backups folder under a
+/// timestamped filename and verify its integrity
+///
+/// `VACUUM INTO` goes through the database engine, so the copy cannot be torn
+/// by a concurrent writer and includes anything still sitting in `-wal` or
+/// `-journal` files, unlike copying the database file directly
+pub async fn create(database: impl AsRef<Path>) -> Result<PathBuf> {
+    let dir = get_backups_directory(&database);
+    create_dir_all(&dir)?;
+    let target = get_unused_path(&dir);
+
+    let source = get_sqlite_connection(&database.as_ref().to_string_lossy()).await?;
+    source
+        .execute(Statement::from_sql_and_values(
+            DatabaseBackend::Sqlite,
+            "VACUUM INTO ?",
+            [target.to_string_lossy().into_owned().into()],
+        ))
+        .await?;
+    source.close().await?;
+    debug!(r#"Wrote backup to "{}""#, target.to_string_lossy());
+
+    let copy = get_sqlite_connection(&target.to_string_lossy()).await?;
+    let integrity = check_integrity(&copy).await;
+    copy.close().await?;
+    match integrity {
+        Ok(None) => Ok(target),
+        Ok(Some(message)) => {
+            remove_file(&target)?;
+            Err(Error::Integrity(message))
+        }
+        Err(err) => {
+            remove_file(&target)?;
+            Err(err.into())
+        }
+    }
+}
+
+/// Backups taken within the same second would share a filename, so later ones
+/// are pushed forward to the next free second instead of overwriting
+fn get_unused_path(dir: &Path) -> PathBuf {
+    let mut time = Utc::now();
+    loop {
+        let path = dir.join(time.format("%Y-%m-%d-%s.sqlite").to_string());
+        if !path.exists() {
+            return path;
+        }
+        time += TimeDelta::seconds(1);
+    }
+}
+
+/// List backups of a database, newest first
+pub fn list(database: impl AsRef<Path>) -> io::Result<Vec<Backup>> {
+    let dir = get_backups_directory(database);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for entry in read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().is_none_or(|ext| ext != "sqlite") {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let created = path
+            .file_stem()
+            .and_then(|stem| parse_timestamp(&stem.to_string_lossy()))
+            .or_else(|| metadata.modified().ok().map(DateTime::<Utc>::from))
+            .unwrap_or_default();
+        backups.push(Backup {
+            path,
+            created,
+            size: metadata.len(),
+        });
+    }
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.created));
+    Ok(backups)
+}
+
+pub async fn get_stats(path: impl AsRef<Path>) -> Result<Stats> {
+    let db = get_sqlite_connection(&path.as_ref().to_string_lossy()).await?;
+    let tracks = library::Entity::find()
+        .filter(library::Column::MixxxDeleted.eq(0))
+        .count(&db)
+        .await?;
+    let crates = crates::Entity::find().count(&db).await?;
+    Ok(Stats { tracks, crates })
+}
+
+/// Backup filenames end in the unix timestamp they were taken at, e.g. `2024-05-01-1714521600`
+fn parse_timestamp(stem: &str) -> Option<DateTime<Utc>> {
+    let (_, seconds) = stem.rsplit_once('-')?;
+    Utc.timestamp_opt(seconds.parse().ok()?, 0).single()
+}
+
+/// Human readable file size
+pub struct Size(pub u64);
+
+impl Display for Size {
+    #[allow(clippy::cast_precision_loss)]
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+        let mut value = self.0 as f64;
+        let mut unit = 0;
+        while value >= 1024.0 && unit < UNITS.len() - 1 {
+            value /= 1024.0;
+            unit += 1;
+        }
+        match unit {
+            0 => write!(f, "{} {}", self.0, UNITS[0]),
+            _ => write!(f, "{value:.1} {}", UNITS[unit]),
+        }
+    }
+}
+
+impl Display for Backup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({}, {})",
+            self.name(),
+            self.created
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S"),
+            Size(self.size)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_timestamp_from_name() {
+        let parsed = parse_timestamp("2024-05-01-1714521600").unwrap();
+        assert_eq!(parsed.timestamp(), 1_714_521_600);
+    }
+
+    #[test]
+    fn ignores_unknown_name() {
+        assert!(parse_timestamp("mixxxdb").is_none());
+    }
+
+    #[test]
+    fn formats_size() {
+        assert_eq!(Size(512).to_string(), "512 B");
+        assert_eq!(Size(1536).to_string(), "1.5 KB");
+        assert_eq!(Size(5 * 1024 * 1024).to_string(), "5.0 MB");
+    }
+}
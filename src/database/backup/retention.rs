@@ -0,0 +1,185 @@
+use super::Backup;
+use chrono::{Datelike, Local};
+use std::collections::HashSet;
+use std::hash::Hash;
+
+/// Rules deciding which backups are kept, where a backup is kept if any rule
+/// keeps it and the total size limit is applied afterwards
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Policy {
+    pub keep_last: Option<usize>,
+    pub keep_daily: Option<usize>,
+    pub keep_weekly: Option<usize>,
+    pub keep_monthly: Option<usize>,
+    pub max_size: Option<u64>,
+}
+
+impl Policy {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn has_keep_rules(&self) -> bool {
+        self.keep_last.is_some()
+            || self.keep_daily.is_some()
+            || self.keep_weekly.is_some()
+            || self.keep_monthly.is_some()
+    }
+}
+
+/// Split backups sorted newest first into the ones to keep and the ones to prune
+pub fn partition(backups: Vec<Backup>, policy: &Policy) -> (Vec<Backup>, Vec<Backup>) {
+    let mut keep = vec![!policy.has_keep_rules(); backups.len()];
+    if let Some(count) = policy.keep_last {
+        keep.iter_mut().take(count).for_each(|flag| *flag = true);
+    }
+    if let Some(count) = policy.keep_daily {
+        mark_buckets(&backups, &mut keep, count, |backup| {
+            backup.created.with_timezone(&Local).date_naive()
+        });
+    }
+    if let Some(count) = policy.keep_weekly {
+        mark_buckets(&backups, &mut keep, count, |backup| {
+            backup.created.with_timezone(&Local).iso_week()
+        });
+    }
+    if let Some(count) = policy.keep_monthly {
+        mark_buckets(&backups, &mut keep, count, |backup| {
+            let local = backup.created.with_timezone(&Local);
+            (local.year(), local.month())
+        });
+    }
+    if let Some(limit) = policy.max_size {
+        let mut total = 0;
+        for (index, backup) in backups.iter().enumerate() {
+            if !keep[index] {
+                continue;
+            }
+            total += backup.size;
+            if total > limit && index > 0 {
+                keep[index] = false;
+            }
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut pruned = Vec::new();
+    for (backup, flag) in backups.into_iter().zip(keep) {
+        match flag {
+            true => kept.push(backup),
+            false => pruned.push(backup),
+        }
+    }
+    (kept, pruned)
+}
+
+/// Keep the newest backup of each of the `count` most recent buckets
+fn mark_buckets<K: Eq + Hash>(
+    backups: &[Backup],
+    keep: &mut [bool],
+    count: usize,
+    bucket: impl Fn(&Backup) -> K,
+) {
+    let mut seen = HashSet::new();
+    for (index, backup) in backups.iter().enumerate() {
+        if seen.len() >= count {
+            break;
+        }
+        if seen.insert(bucket(backup)) {
+            keep[index] = true;
+        }
+    }
+}
+
+/// Parse sizes such as `500MB` or `2 GB` into bytes
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let trimmed = input.trim();
+    let split = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (digits, unit) = trimmed.split_at(split);
+    let value: u64 = digits
+        .parse()
+        .map_err(|_| format!(r#"Invalid size "{input}""#))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1024,
+        "M" | "MB" => 1024 * 1024,
+        "G" | "GB" => 1024 * 1024 * 1024,
+        _ => return Err(format!(r#"Unknown size unit "{unit}""#)),
+    };
+    value
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!(r#"Size "{input}" is too large"#))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::path::PathBuf;
+
+    fn backups(days_ago: &[i64]) -> Vec<Backup> {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        days_ago
+            .iter()
+            .map(|days| Backup {
+                path: PathBuf::from(format!("{days}.sqlite")),
+                created: now - chrono::TimeDelta::days(*days),
+                size: 10,
+            })
+            .collect()
+    }
+
+    fn names(backups: &[Backup]) -> Vec<String> {
+        backups.iter().map(Backup::name).collect()
+    }
+
+    #[test]
+    fn keeps_everything_without_rules() {
+        let (kept, pruned) = partition(backups(&[0, 1, 2]), &Policy::default());
+        assert_eq!(kept.len(), 3);
+        assert!(pruned.is_empty());
+    }
+
+    #[test]
+    fn keeps_last() {
+        let policy = Policy {
+            keep_last: Some(2),
+            ..Policy::default()
+        };
+        let (kept, pruned) = partition(backups(&[0, 1, 2, 3]), &policy);
+        assert_eq!(names(&kept), ["0.sqlite", "1.sqlite"]);
+        assert_eq!(names(&pruned), ["2.sqlite", "3.sqlite"]);
+    }
+
+    #[test]
+    fn keeps_newest_per_month() {
+        let policy = Policy {
+            keep_monthly: Some(2),
+            ..Policy::default()
+        };
+        let (kept, _) = partition(backups(&[0, 1, 30, 31, 70]), &policy);
+        assert_eq!(names(&kept), ["0.sqlite", "30.sqlite"]);
+    }
+
+    #[test]
+    fn limits_total_size() {
+        let policy = Policy {
+            max_size: Some(25),
+            ..Policy::default()
+        };
+        let (kept, pruned) = partition(backups(&[0, 1, 2, 3]), &policy);
+        assert_eq!(names(&kept), ["0.sqlite", "1.sqlite"]);
+        assert_eq!(pruned.len(), 2);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("2 KB"), Ok(2048));
+        assert_eq!(parse_size("1gb"), Ok(1024 * 1024 * 1024));
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("18446744073709551615 GB").is_err());
+    }
+}
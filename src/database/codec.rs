@@ -0,0 +1,558 @@
+//! Mixxx's protobuf encoded `beats` and `keys` blobs, as described by
+//! `beats.proto` and `keys.proto` in the Mixxx sources
+
+pub const BEAT_GRID: &str = "BeatGrid-2.0";
+pub const BEAT_MAP: &str = "BeatMap-1.0";
+pub const KEY_MAP: &str = "KeyMap-1.0";
+
+/// Beats of a track, with positions in frames
+#[derive(Clone, Debug, PartialEq)]
+pub enum Beats {
+    /// Constant tempo starting at the first beat
+    Grid {
+        bpm: f64,
+        first_beat: i32,
+        source: Source,
+    },
+    /// Every enabled beat of a track with variable tempo
+    Map { beats: Vec<i32>, source: Source },
+}
+
+impl Beats {
+    /// Decode the `beats` column according to `beats_version`
+    pub fn decode(version: &str, data: &[u8]) -> Option<Beats> {
+        match version {
+            BEAT_GRID => decode_grid(data),
+            BEAT_MAP => decode_map(data),
+            _ => None,
+        }
+    }
+
+    /// Encode into the `beats` column, which has to be stored along with
+    /// [`Beats::get_version`] as `beats_version`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut message = Message::default();
+        match self {
+            Beats::Grid {
+                bpm,
+                first_beat,
+                source,
+            } => {
+                let mut tempo = Message::default();
+                tempo.fixed64(1, bpm.to_bits());
+                // Analyzer is the default source and left out, as Mixxx does
+                if *source != Source::Analyzer {
+                    tempo.varint(2, source.to_value());
+                }
+                message.bytes(1, &tempo.0);
+                message.bytes(2, &encode_beat(*first_beat, *source));
+            }
+            Beats::Map { beats, source } => {
+                for beat in beats {
+                    message.bytes(1, &encode_beat(*beat, *source));
+                }
+            }
+        }
+        message.0
+    }
+
+    pub fn get_version(&self) -> &'static str {
+        match self {
+            Beats::Grid { .. } => BEAT_GRID,
+            Beats::Map { .. } => BEAT_MAP,
+        }
+    }
+
+    pub fn get_source(&self) -> Source {
+        match self {
+            Beats::Grid { source, .. } | Beats::Map { source, .. } => *source,
+        }
+    }
+
+    pub fn set_source(&mut self, to: Source) {
+        match self {
+            Beats::Grid { source, .. } | Beats::Map { source, .. } => *source = to,
+        }
+    }
+
+    pub fn get_first_beat(&self) -> Option<i32> {
+        match self {
+            Beats::Grid { first_beat, .. } => Some(*first_beat),
+            Beats::Map { beats, .. } => beats.first().copied(),
+        }
+    }
+
+    /// Tempo of a grid, or the average tempo of a map
+    pub fn get_bpm(&self, sample_rate: i32) -> Option<f64> {
+        match self {
+            Beats::Grid { bpm, .. } => Some(*bpm),
+            Beats::Map { beats, .. } => {
+                let (first, last) = (beats.first()?, beats.last()?);
+                let frames = f64::from(last - first);
+                let intervals = f64::from(u32::try_from(beats.len() - 1).ok()?);
+                (frames > 0.0).then(|| 60.0 * f64::from(sample_rate) * intervals / frames)
+            }
+        }
+    }
+
+    /// Move every beat by a number of frames
+    pub fn shift(&mut self, frames: i32) {
+        match self {
+            Beats::Grid { first_beat, .. } => *first_beat = first_beat.saturating_add(frames),
+            Beats::Map { beats, .. } => {
+                for beat in beats {
+                    *beat = beat.saturating_add(frames);
+                }
+            }
+        }
+    }
+
+    /// Halve the tempo, dropping every other beat of a map
+    pub fn halve(&mut self) {
+        match self {
+            Beats::Grid { bpm, .. } => *bpm /= 2.0,
+            Beats::Map { beats, .. } => *beats = beats.iter().step_by(2).copied().collect(),
+        }
+    }
+
+    /// Double the tempo, adding a beat halfway between each beat of a map
+    pub fn double(&mut self) {
+        match self {
+            Beats::Grid { bpm, .. } => *bpm *= 2.0,
+            Beats::Map { beats, .. } => {
+                let mut doubled = Vec::with_capacity(beats.len() * 2);
+                for pair in beats.windows(2) {
+                    doubled.extend([pair[0], pair[0] + (pair[1] - pair[0]) / 2]);
+                }
+                doubled.extend(beats.last());
+                *beats = doubled;
+            }
+        }
+    }
+
+    /// Constant grid at the nearest whole tempo, starting at the first beat
+    pub fn snap(&self, sample_rate: i32) -> Option<Beats> {
+        let bpm = self.get_bpm(sample_rate)?.round();
+        (bpm > 0.0).then(|| Beats::Grid {
+            bpm,
+            first_beat: self.get_first_beat().unwrap_or_default(),
+            source: self.get_source(),
+        })
+    }
+}
+
+/// `BeatGrid { Bpm bpm = 1; Beat first_beat = 2; }` with
+/// `Bpm { double bpm = 1; Source source = 2; }`, where the source of the tempo
+/// stands for that of the whole grid
+fn decode_grid(data: &[u8]) -> Option<Beats> {
+    let (mut bpm, mut first_beat, mut source) = (None, None, Source::Analyzer);
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Bytes(message)) => {
+                for field in Fields(message) {
+                    match field? {
+                        (1, Value::Fixed64(bits)) => bpm = Some(f64::from_bits(bits)),
+                        (2, Value::Varint(value)) => source = Source::from_value(value),
+                        _ => {}
+                    }
+                }
+            }
+            (2, Value::Bytes(message)) => first_beat = Some(decode_beat(message)?.frame),
+            _ => {}
+        }
+    }
+    Some(Beats::Grid {
+        bpm: bpm?,
+        first_beat: first_beat.unwrap_or_default(),
+        source,
+    })
+}
+
+/// `BeatMap { repeated Beat beat = 1; }`, where the source of the first beat
+/// stands for that of the whole map
+fn decode_map(data: &[u8]) -> Option<Beats> {
+    let (mut beats, mut source) = (Vec::new(), None);
+    for field in Fields(data) {
+        if let (1, Value::Bytes(message)) = field? {
+            let beat = decode_beat(message)?;
+            source.get_or_insert(beat.source);
+            if beat.enabled {
+                beats.push(beat.frame);
+            }
+        }
+    }
+    Some(Beats::Map {
+        beats,
+        source: source.unwrap_or_default(),
+    })
+}
+
+struct Beat {
+    frame: i32,
+    enabled: bool,
+    source: Source,
+}
+
+/// `Beat { int32 frame_position = 1; bool enabled = 2 [default = true];
+/// Source source = 3; }`
+fn decode_beat(data: &[u8]) -> Option<Beat> {
+    let mut beat = Beat {
+        frame: 0,
+        enabled: true,
+        source: Source::Analyzer,
+    };
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Varint(value)) => beat.frame = to_int32(value),
+            (2, Value::Varint(value)) => beat.enabled = value != 0,
+            (3, Value::Varint(value)) => beat.source = Source::from_value(value),
+            _ => {}
+        }
+    }
+    Some(beat)
+}
+
+fn encode_beat(frame: i32, source: Source) -> Vec<u8> {
+    let mut message = Message::default();
+    message.varint(1, from_int32(frame));
+    if source != Source::Analyzer {
+        message.varint(3, source.to_value());
+    }
+    message.0
+}
+
+/// Whoever determined the beats or keys of a track
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Source {
+    #[default]
+    Analyzer,
+    FileMetadata,
+    User,
+}
+
+impl Source {
+    fn from_value(value: u64) -> Source {
+        match value {
+            1 => Source::FileMetadata,
+            2 => Source::User,
+            _ => Source::Analyzer,
+        }
+    }
+
+    fn to_value(self) -> u64 {
+        match self {
+            Source::Analyzer => 0,
+            Source::FileMetadata => 1,
+            Source::User => 2,
+        }
+    }
+}
+
+/// Keys of a track, numbered like `key_id` from 1 for C major to 12 for B
+/// major and from 13 for C minor to 24 for B minor, with 0 being invalid
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Keys {
+    pub global_key: i32,
+    pub changes: Vec<KeyChange>,
+    pub source: Source,
+    /// The key as written in the tags of the file, if it came from there
+    pub global_key_text: Option<String>,
+}
+
+/// Key that a track changes to at a position in frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KeyChange {
+    pub key: i32,
+    pub frame: i32,
+}
+
+impl Keys {
+    /// Decode the `keys` column according to `keys_version`
+    ///
+    /// `KeyMap { Key global_key = 1; repeated KeyChange key_change = 2;
+    /// Source source = 3; string global_key_text = 4; }`
+    pub fn decode(version: &str, data: &[u8]) -> Option<Keys> {
+        if version != KEY_MAP {
+            return None;
+        }
+        let mut keys = Keys::default();
+        for field in Fields(data) {
+            match field? {
+                (1, Value::Varint(value)) => keys.global_key = to_int32(value),
+                (2, Value::Bytes(message)) => keys.changes.push(decode_key_change(message)?),
+                (3, Value::Varint(value)) => keys.source = Source::from_value(value),
+                (4, Value::Bytes(text)) => {
+                    keys.global_key_text = Some(String::from_utf8_lossy(text).into_owned());
+                }
+                _ => {}
+            }
+        }
+        Some(keys)
+    }
+
+    /// Encode into the `keys` column, which has to be stored along with
+    /// [`KEY_MAP`] as `keys_version`
+    pub fn encode(&self) -> Vec<u8> {
+        let mut message = Message::default();
+        message.varint(1, from_int32(self.global_key));
+        for change in &self.changes {
+            let mut inner = Message::default();
+            inner
+                .varint(1, from_int32(change.key))
+                .varint(2, from_int32(change.frame));
+            message.bytes(2, &inner.0);
+        }
+        message.varint(3, self.source.to_value());
+        if let Some(text) = &self.global_key_text {
+            message.bytes(4, text.as_bytes());
+        }
+        message.0
+    }
+}
+
+/// `KeyChange { Key key = 1; int32 frame_position = 2; }`
+fn decode_key_change(data: &[u8]) -> Option<KeyChange> {
+    let mut change = KeyChange { key: 0, frame: 0 };
+    for field in Fields(data) {
+        match field? {
+            (1, Value::Varint(value)) => change.key = to_int32(value),
+            (2, Value::Varint(value)) => change.frame = to_int32(value),
+            _ => {}
+        }
+    }
+    Some(change)
+}
+
+/// Negative `int32` values are sign extended to ten byte varints
+#[allow(clippy::cast_possible_truncation)]
+fn to_int32(value: u64) -> i32 {
+    value as i32
+}
+
+#[allow(clippy::cast_sign_loss)]
+fn from_int32(value: i32) -> u64 {
+    i64::from(value) as u64
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    /// Not used by any message of Mixxx, so only skipped
+    Fixed32,
+}
+
+/// Fields of a protobuf message as field numbers and values, yielding `None`
+/// once and stopping when the message is malformed
+struct Fields<'a>(&'a [u8]);
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = Option<(u64, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let field = self.read_field();
+        if field.is_none() {
+            self.0 = &[];
+        }
+        Some(field)
+    }
+}
+
+impl<'a> Fields<'a> {
+    fn read_field(&mut self) -> Option<(u64, Value<'a>)> {
+        let key = self.read_varint()?;
+        let value = match key & 7 {
+            0 => Value::Varint(self.read_varint()?),
+            1 => Value::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().ok()?)),
+            2 => {
+                let length = usize::try_from(self.read_varint()?).ok()?;
+                Value::Bytes(self.take(length)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed32
+            }
+            _ => return None,
+        };
+        Some((key >> 3, value))
+    }
+
+    fn read_varint(&mut self) -> Option<u64> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let (byte, rest) = self.0.split_first()?;
+            self.0 = rest;
+            value |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    fn take(&mut self, length: usize) -> Option<&'a [u8]> {
+        let taken = self.0.get(..length)?;
+        self.0 = &self.0[length..];
+        Some(taken)
+    }
+}
+
+/// Protobuf message being written, with fields in the order they are added
+#[derive(Default)]
+struct Message(Vec<u8>);
+
+impl Message {
+    fn varint(&mut self, field: u64, value: u64) -> &mut Self {
+        self.write_varint(field << 3);
+        self.write_varint(value);
+        self
+    }
+
+    fn fixed64(&mut self, field: u64, value: u64) -> &mut Self {
+        self.write_varint(field << 3 | 1);
+        self.0.extend(value.to_le_bytes());
+        self
+    }
+
+    fn bytes(&mut self, field: u64, data: &[u8]) -> &mut Self {
+        self.write_varint(field << 3 | 2);
+        self.write_varint(data.len() as u64);
+        self.0.extend(data);
+        self
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.0.push((value & 0x7F) as u8 | 0x80);
+            value >>= 7;
+        }
+        self.0.push(value as u8);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_beat_grid() {
+        // bpm { bpm: 128.0 } first_beat { frame_position: 1234 }
+        let mut data = vec![0x0A, 0x09, 0x09];
+        data.extend(128f64.to_le_bytes());
+        data.extend([0x12, 0x03, 0x08, 0xD2, 0x09]);
+        assert_eq!(
+            Beats::decode(BEAT_GRID, &data),
+            Some(Beats::Grid {
+                bpm: 128.0,
+                first_beat: 1234,
+                source: Source::Analyzer,
+            })
+        );
+        assert_eq!(Beats::decode(BEAT_GRID, &data[..5]), None);
+    }
+
+    #[test]
+    fn encodes_what_it_decodes() {
+        let mut data = vec![0x0A, 0x09, 0x09];
+        data.extend(128f64.to_le_bytes());
+        data.extend([0x12, 0x03, 0x08, 0xD2, 0x09]);
+        let grid = Beats::decode(BEAT_GRID, &data).unwrap();
+        assert_eq!(grid.encode(), data);
+
+        let map = Beats::Map {
+            beats: vec![-300, 0, 44100],
+            source: Source::Analyzer,
+        };
+        assert_eq!(Beats::decode(map.get_version(), &map.encode()), Some(map));
+    }
+
+    #[test]
+    fn keeps_source_of_beats() {
+        // bpm { bpm: 128.0 source: USER } first_beat { frame_position: 1234 source: USER }
+        let mut data = vec![0x0A, 0x0B, 0x09];
+        data.extend(128f64.to_le_bytes());
+        data.extend([0x10, 0x02, 0x12, 0x05, 0x08, 0xD2, 0x09, 0x18, 0x02]);
+        let grid = Beats::decode(BEAT_GRID, &data).unwrap();
+        assert_eq!(grid.get_source(), Source::User);
+        assert_eq!(grid.encode(), data);
+
+        // beat { frame_position: 10 source: FILE_METADATA } beat { frame_position: 20 source: FILE_METADATA }
+        let data = [
+            0x0A, 0x04, 0x08, 0x0A, 0x18, 0x01, 0x0A, 0x04, 0x08, 0x14, 0x18, 0x01,
+        ];
+        let map = Beats::decode(BEAT_MAP, &data).unwrap();
+        assert_eq!(
+            map,
+            Beats::Map {
+                beats: vec![10, 20],
+                source: Source::FileMetadata,
+            }
+        );
+        assert_eq!(map.encode(), data);
+    }
+
+    #[test]
+    fn edits_beat_maps() {
+        let map = |beats| Beats::Map {
+            beats,
+            source: Source::User,
+        };
+        let mut edited = map(vec![0, 22050, 44100, 66150, 88200]);
+        assert_eq!(edited.get_bpm(44100), Some(120.0));
+        edited.halve();
+        assert_eq!(edited, map(vec![0, 44100, 88200]));
+        edited.double();
+        edited.shift(-100);
+        assert_eq!(edited, map(vec![-100, 21950, 44000, 66050, 88100]));
+        assert_eq!(
+            map(vec![0, 21800, 43600]).snap(44100),
+            Some(Beats::Grid {
+                bpm: 121.0,
+                first_beat: 0,
+                source: Source::User,
+            })
+        );
+        assert_eq!(map(vec![5]).snap(44100), None);
+    }
+
+    #[test]
+    fn decodes_and_encodes_key_map() {
+        // global_key: A_MINOR key_change { key: A_MINOR frame_position: 0 }
+        // source: FILE_METADATA global_key_text: "Am"
+        let data = [
+            0x08, 0x16, 0x12, 0x04, 0x08, 0x16, 0x10, 0x00, 0x18, 0x01, 0x22, 0x02, b'A', b'm',
+        ];
+        let keys = Keys::decode(KEY_MAP, &data).unwrap();
+        assert_eq!(
+            keys,
+            Keys {
+                global_key: 22,
+                changes: vec![KeyChange { key: 22, frame: 0 }],
+                source: Source::FileMetadata,
+                global_key_text: Some("Am".into()),
+            }
+        );
+        assert_eq!(keys.encode(), data);
+        assert_eq!(Keys::decode("KeyMap-0.1", &data), None);
+    }
+
+    #[test]
+    fn decodes_beat_map_skipping_disabled_beats() {
+        // beat { frame_position: 10 } beat { frame_position: 20 enabled: false } beat { frame_position: 300 }
+        let data = [
+            0x0A, 0x02, 0x08, 0x0A, 0x0A, 0x04, 0x08, 0x14, 0x10, 0x00, 0x0A, 0x03, 0x08, 0xAC,
+            0x02,
+        ];
+        assert_eq!(
+            Beats::decode(BEAT_MAP, &data),
+            Some(Beats::Map {
+                beats: vec![10, 300],
+                source: Source::Analyzer,
+            })
+        );
+    }
+}
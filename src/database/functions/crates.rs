@@ -1,9 +1,53 @@
-use crate::database::schema::{crate_tracks, crates};
-use log::debug;
+use super::report::{Outcome, Report, Section};
+use super::{get_unused_name, tracks::TrackMapping, NameConflict};
+use crate::database::schema::{crate_tracks, crates, library, track_locations};
+use log::{debug, warn};
 use sea_orm::{
     sea_query::OnConflict, ActiveValue, ColumnTrait, ConnectionTrait, DbErr, EntityTrait,
-    InsertResult, QueryFilter, TryInsertResult,
+    FromQueryResult, InsertResult, IntoActiveModel, JoinType, QueryFilter, QueryOrder, QuerySelect,
+    RelationTrait, TryInsertResult,
 };
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+
+pub async fn get<C: ConnectionTrait>(db: &C) -> Result<Vec<crates::Model>, DbErr> {
+    crates::Entity::find().all(db).await
+}
+
+pub async fn get_tracks<C: ConnectionTrait>(db: &C) -> Result<Vec<crate_tracks::Model>, DbErr> {
+    crate_tracks::Entity::find().all(db).await
+}
+
+/// A track in a crate along with the file it points to
+#[derive(Debug, FromQueryResult)]
+pub struct Entry {
+    pub location: Option<String>,
+    pub artist: Option<String>,
+    pub title: Option<String>,
+    pub duration: Option<f64>,
+}
+
+/// Tracks in a crate that have not been removed from the library, sorted by artist and title
+pub async fn get_entries<C: ConnectionTrait>(db: &C, crate_id: i32) -> Result<Vec<Entry>, DbErr> {
+    library::Entity::find()
+        .select_only()
+        .column(track_locations::Column::Location)
+        .column(library::Column::Artist)
+        .column(library::Column::Title)
+        .column(library::Column::Duration)
+        .inner_join(track_locations::Entity)
+        .join(
+            JoinType::InnerJoin,
+            crate_tracks::Relation::Library.def().rev(),
+        )
+        .filter(crate_tracks::Column::CrateId.eq(crate_id))
+        .filter(library::Column::MixxxDeleted.eq(0))
+        .order_by_asc(library::Column::Artist)
+        .order_by_asc(library::Column::Title)
+        .into_model::<Entry>()
+        .all(db)
+        .await
+}
 
 pub async fn get_by_id<C: ConnectionTrait>(
     db: &C,
@@ -12,11 +56,45 @@ pub async fn get_by_id<C: ConnectionTrait>(
     crates::Entity::find_by_id(id).one(db).await
 }
 
-pub async fn get_by_name_or_create<C: ConnectionTrait>(db: &C, name: &str) -> Result<i32, DbErr> {
-    let crate_maybe = crates::Entity::find()
+pub async fn get_by_name<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+) -> Result<Option<crates::Model>, DbErr> {
+    crates::Entity::find()
         .filter(crates::Column::Name.eq(name))
         .one(db)
-        .await?;
+        .await
+}
+
+/// Ids of the tracks in the named crates, or `None` if no crates were named
+pub async fn get_track_ids_by_names<C: ConnectionTrait>(
+    db: &C,
+    names: &[String],
+) -> Result<Option<HashSet<i32>>, DbErr> {
+    if names.is_empty() {
+        return Ok(None);
+    }
+    let mut crate_ids = HashSet::new();
+    for name in names {
+        match get_by_name(db, name).await? {
+            Some(found) => {
+                crate_ids.insert(found.id);
+            }
+            None => warn!(r#"Could not find crate "{name}"! Skipping..."#),
+        }
+    }
+    Ok(Some(
+        get_tracks(db)
+            .await?
+            .into_iter()
+            .filter(|entry| crate_ids.contains(&entry.crate_id))
+            .map(|entry| entry.track_id)
+            .collect(),
+    ))
+}
+
+pub async fn get_by_name_or_create<C: ConnectionTrait>(db: &C, name: &str) -> Result<i32, DbErr> {
+    let crate_maybe = get_by_name(db, name).await?;
     if let Some(track_crate) = crate_maybe {
         debug!(r#"Found crate "{name}" with id "{}""#, track_crate.id);
         return Ok(track_crate.id);
@@ -63,3 +141,102 @@ pub async fn connect_track<C: ConnectionTrait>(
         .exec(db)
         .await
 }
+
+pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
+    db: &C,
+    crates: Vec<crates::Model>,
+    crate_tracks: Vec<crate_tracks::Model>,
+    track_map: &HashMap<i32, TrackMapping, S>,
+    conflict: NameConflict,
+    report: &mut Report,
+) -> Result<(), DbErr> {
+    let mut crate_map = HashMap::with_capacity(crates.len());
+    for source in crates {
+        let prev_id = source.id;
+        let name = source.name.clone();
+        let subject = format!(r#""{name}""#);
+        let existing = get_by_name(db, &name).await?;
+        let mapped_id = match (existing, conflict) {
+            (None, _) => {
+                let id = create_from(db, source, &name).await?;
+                report.record(Section::Crates, Outcome::Added, subject, "new");
+                id
+            }
+            (Some(found), NameConflict::Union) => {
+                debug!(
+                    r#"Merging crate "{name}" into existing crate id "{}""#,
+                    found.id
+                );
+                report.record(
+                    Section::Crates,
+                    Outcome::Conflicted,
+                    subject,
+                    "merged into existing crate",
+                );
+                found.id
+            }
+            (Some(_), NameConflict::Rename) => {
+                let renamed = get_unused_name(&name, |candidate| async move {
+                    Ok(get_by_name(db, &candidate).await?.is_some())
+                })
+                .await?;
+                let id = create_from(db, source, &renamed).await?;
+                report.record(
+                    Section::Crates,
+                    Outcome::Remapped,
+                    subject,
+                    format!(r#"renamed to "{renamed}""#),
+                );
+                id
+            }
+            (Some(_), NameConflict::Skip) => {
+                warn!(r#"Crate "{name}" already exists! Skipping..."#);
+                report.record(Section::Crates, Outcome::Skipped, subject, "name is taken");
+                continue;
+            }
+        };
+        crate_map.insert(prev_id, mapped_id);
+    }
+
+    for crate_track in crate_tracks {
+        let (Some(crate_id), Some(track_id)) = (
+            crate_map.get(&crate_track.crate_id),
+            track_map.get(&crate_track.track_id).map(|t| t.id()),
+        ) else {
+            debug!(
+                r#"Could not map track id "{}" in crate id "{}"! Skipping..."#,
+                crate_track.track_id, crate_track.crate_id
+            );
+            report.record(
+                Section::Crates,
+                Outcome::Skipped,
+                format!(
+                    r#"Track id "{}" in crate id "{}""#,
+                    crate_track.track_id, crate_track.crate_id
+                ),
+                "track or crate was not merged",
+            );
+            continue;
+        };
+        connect_track(db, *crate_id, track_id).await?;
+    }
+    Ok(())
+}
+
+async fn create_from<C: ConnectionTrait>(
+    db: &C,
+    source: crates::Model,
+    name: &str,
+) -> Result<i32, DbErr> {
+    let data = crates::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(name.to_owned()),
+        ..source.into_active_model()
+    };
+    let result = crates::Entity::insert(data).exec(db).await?;
+    debug!(
+        r#"Created crate "{name}" with id "{}""#,
+        result.last_insert_id
+    );
+    Ok(result.last_insert_id)
+}
@@ -0,0 +1,154 @@
+use super::report::{Outcome, Report, Section};
+use super::tracks::TrackMapping;
+use crate::database::schema::cues;
+use log::{debug, warn};
+use sea_orm::sea_query::Expr;
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel, QueryFilter,
+    QuerySelect,
+};
+use std::collections::{HashMap, HashSet};
+use std::hash::BuildHasher;
+
+/// Cue types as numbered by Mixxx's `CueType`
+pub const TYPE_HOTCUE: i32 = 1;
+pub const TYPE_MAIN: i32 = 2;
+pub const TYPE_LOOP: i32 = 4;
+
+/// Get cues from database, accounting for the fact that `position` is set to
+/// `Integer` but Mixxx may have inserted values that are `Real`
+pub async fn get<C: ConnectionTrait>(db: &C) -> Result<Vec<cues::Model>, DbErr> {
+    cues::Entity::find()
+        .select_only()
+        .columns([
+            cues::Column::Id,
+            cues::Column::TrackId,
+            cues::Column::Type,
+            cues::Column::Length,
+            cues::Column::Hotcue,
+            cues::Column::Label,
+            cues::Column::Color,
+        ])
+        .column_as(
+            Expr::cust("CAST(ROUND(\"position\") AS INTEGER)"),
+            "position",
+        )
+        .into_model::<cues::Model>()
+        .all(db)
+        .await
+}
+
+pub async fn has_any<C: ConnectionTrait>(db: &C, track_id: i32) -> Result<bool, DbErr> {
+    let found = cues::Entity::find()
+        .select_only()
+        .column(cues::Column::Id)
+        .filter(cues::Column::TrackId.eq(track_id))
+        .into_tuple::<i32>()
+        .one(db)
+        .await?;
+    Ok(found.is_some())
+}
+
+pub async fn delete_by_track<C: ConnectionTrait>(db: &C, track_id: i32) -> Result<(), DbErr> {
+    cues::Entity::delete_many()
+        .filter(cues::Column::TrackId.eq(track_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Insert a cue, leaving the color to the column default when not given
+pub async fn create<C: ConnectionTrait>(db: &C, data: cues::ActiveModel) -> Result<i32, DbErr> {
+    let result = cues::Entity::insert(data).exec(db).await?;
+    debug!(r#"Created cue with id "{}""#, result.last_insert_id);
+    Ok(result.last_insert_id)
+}
+
+/// Insert cues against their mapped tracks. Cues of replaced tracks are
+/// swapped for the source cues, filled tracks only receive source cues if they
+/// had none, and kept tracks are left alone.
+pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
+    db: &C,
+    cues: Vec<cues::Model>,
+    track_map: &HashMap<i32, TrackMapping, S>,
+    report: &mut Report,
+) -> Result<(), DbErr> {
+    let replaced: Vec<i32> = track_map
+        .values()
+        .filter_map(|mapping| match mapping {
+            TrackMapping::Replaced(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    cues::Entity::delete_many()
+        .filter(cues::Column::TrackId.is_in(replaced))
+        .exec(db)
+        .await?;
+
+    let filled: Vec<i32> = track_map
+        .values()
+        .filter_map(|mapping| match mapping {
+            TrackMapping::Filled(id) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let with_cues: HashSet<i32> = cues::Entity::find()
+        .select_only()
+        .column(cues::Column::TrackId)
+        .filter(cues::Column::TrackId.is_in(filled))
+        .into_tuple()
+        .all(db)
+        .await?
+        .into_iter()
+        .collect();
+
+    for cue in cues {
+        let prev_id = cue.id;
+        let prev_track_id = cue.track_id;
+        let subject = format!(
+            r#"Cue "{}" of type {} on track id "{prev_track_id}""#,
+            cue.label, cue.r#type
+        );
+        let Some(mapping) = track_map.get(&prev_track_id) else {
+            warn!(
+                r#"Could not find new track of cue with id "{prev_id}" and track id "{prev_track_id}"! Skipping..."#
+            );
+            report.record(
+                Section::Cues,
+                Outcome::Skipped,
+                subject,
+                "track was not merged",
+            );
+            continue;
+        };
+        let (mapped_track_id, outcome, reason) = match mapping {
+            TrackMapping::Inserted(id) => (*id, Outcome::Added, "new track"),
+            TrackMapping::Replaced(id) => (*id, Outcome::Conflicted, "replaced target cues"),
+            TrackMapping::Filled(id) if !with_cues.contains(id) => {
+                (*id, Outcome::Conflicted, "target had no cues")
+            }
+            TrackMapping::Filled(_) | TrackMapping::Kept(_) => {
+                debug!(r#"Keeping existing cues of track id "{}""#, mapping.id());
+                report.record(Section::Cues, Outcome::Skipped, subject, "kept target cues");
+                continue;
+            }
+        };
+        let data = cues::ActiveModel {
+            id: ActiveValue::NotSet,
+            track_id: ActiveValue::Set(mapped_track_id),
+            ..cue.into_active_model()
+        };
+        let result = cues::Entity::insert(data).exec(db).await?;
+        debug!(
+            r#"Created cue with id "{}", mapping track id from "{prev_track_id}" to "{mapped_track_id}""#,
+            result.last_insert_id
+        );
+        report.record(
+            Section::Cues,
+            outcome,
+            subject,
+            format!(r#"{reason} as track id "{mapped_track_id}""#),
+        );
+    }
+    Ok(())
+}
@@ -1,3 +1,4 @@
+use super::report::{Outcome, Report, Section};
 use crate::database::schema::directories;
 use log::{debug, warn};
 use sea_orm::{ActiveValue, ConnectionTrait, DbErr, EntityTrait};
@@ -11,27 +12,43 @@ pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
     db: &C,
     directories: &[directories::Model],
     directory_map: Option<&HashMap<String, String, S>>,
+    report: &mut Report,
 ) -> Result<(), DbErr> {
     for dir in directories {
         let directory = &dir.directory;
+        let mapped = directory_map.and_then(|map| map.get(directory));
         let data = directories::ActiveModel {
-            directory: directory_map
-                .and_then(|map| map.get(directory))
-                .map_or_else(
-                    || {
-                        debug!(r#"Merging directory "{directory}" unchanged"#);
-                        ActiveValue::Unchanged(directory.to_string())
-                    },
-                    |val| {
-                        debug!(r#"Merging directory "{directory}" as "{val}""#);
-                        ActiveValue::Set(val.clone())
-                    },
-                ),
+            directory: mapped.map_or_else(
+                || {
+                    debug!(r#"Merging directory "{directory}" unchanged"#);
+                    ActiveValue::Unchanged(directory.clone())
+                },
+                |val| {
+                    debug!(r#"Merging directory "{directory}" as "{val}""#);
+                    ActiveValue::Set(val.clone())
+                },
+            ),
         };
+        let subject = format!(r#""{directory}""#);
         let Ok(_) = directories::Entity::insert(data).exec(db).await else {
             warn!(r#"Could not insert directory "{directory}"! Skipping..."#,);
+            report.record(
+                Section::Directories,
+                Outcome::Skipped,
+                subject,
+                "already in target",
+            );
             continue;
         };
+        match mapped {
+            Some(val) => report.record(
+                Section::Directories,
+                Outcome::Remapped,
+                subject,
+                format!(r#"moved to "{val}""#),
+            ),
+            None => report.record(Section::Directories, Outcome::Added, subject, "new"),
+        }
     }
     Ok(())
 }
@@ -0,0 +1,27 @@
+//! The iTunes library that Mixxx caches when it is enabled as an external library
+
+use crate::database::schema::{itunes_library, itunes_playlist_tracks, itunes_playlists};
+use sea_orm::{ConnectionTrait, DbErr, EntityTrait, QueryOrder};
+
+pub async fn get<C: ConnectionTrait>(db: &C) -> Result<Vec<itunes_library::Model>, DbErr> {
+    itunes_library::Entity::find().all(db).await
+}
+
+pub async fn get_playlists<C: ConnectionTrait>(
+    db: &C,
+) -> Result<Vec<itunes_playlists::Model>, DbErr> {
+    itunes_playlists::Entity::find()
+        .order_by_asc(itunes_playlists::Column::Id)
+        .all(db)
+        .await
+}
+
+pub async fn get_playlist_tracks<C: ConnectionTrait>(
+    db: &C,
+) -> Result<Vec<itunes_playlist_tracks::Model>, DbErr> {
+    itunes_playlist_tracks::Entity::find()
+        .order_by_asc(itunes_playlist_tracks::Column::PlaylistId)
+        .order_by_asc(itunes_playlist_tracks::Column::Position)
+        .all(db)
+        .await
+}
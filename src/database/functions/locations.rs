@@ -1,16 +1,32 @@
+use super::report::{Outcome, Report, Section};
 use crate::database::schema::track_locations;
 use log::{debug, warn};
-use sea_orm::{ActiveValue, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel};
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel, QueryFilter,
+};
 use std::{collections::HashMap, hash::BuildHasher};
 
 pub async fn get<C: ConnectionTrait>(db: &C) -> Result<Vec<track_locations::Model>, DbErr> {
     track_locations::Entity::find().all(db).await
 }
 
+pub async fn get_by_path<C: ConnectionTrait>(
+    db: &C,
+    path: &str,
+) -> Result<Option<track_locations::Model>, DbErr> {
+    track_locations::Entity::find()
+        .filter(track_locations::Column::Location.eq(path))
+        .one(db)
+        .await
+}
+
+/// Insert locations that are missing from the target and return a map from
+/// source ids to target ids, reusing rows of locations that already exist
 pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
     db: &C,
     locations: Vec<track_locations::Model>,
     directory_map: Option<&HashMap<String, String, S>>,
+    report: &mut Report,
 ) -> Result<HashMap<i32, i32>, DbErr> {
     let mut location_map = HashMap::with_capacity(locations.len());
     for loc in locations {
@@ -28,11 +44,45 @@ pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
             ActiveValue::NotSet => None,
         }
         .unwrap_or_else(|| "<N/A>".to_owned());
+        if let Some(existing) = get_by_path(db, &path).await? {
+            location_map.insert(prev_id, existing.id);
+            report.record(
+                Section::Locations,
+                Outcome::Conflicted,
+                format!(r#""{path}""#),
+                format!(r#"matched existing id "{}""#, existing.id),
+            );
+            debug!(
+                r#"Matched "{prev_path}" with id "{prev_id}" to existing "{path}" with id "{}""#,
+                existing.id
+            );
+            continue;
+        }
         let Ok(result) = track_locations::Entity::insert(data).exec(db).await else {
             warn!(r#"Could not insert location "{path}"! Skipping..."#,);
+            report.record(
+                Section::Locations,
+                Outcome::Skipped,
+                format!(r#""{path}""#),
+                "could not insert",
+            );
             continue;
         };
         location_map.insert(prev_id, result.last_insert_id);
+        match prev_path == path {
+            true => report.record(
+                Section::Locations,
+                Outcome::Added,
+                format!(r#""{path}""#),
+                "new",
+            ),
+            false => report.record(
+                Section::Locations,
+                Outcome::Remapped,
+                format!(r#""{path}""#),
+                format!(r#"moved from "{prev_path}""#),
+            ),
+        }
         debug!(
             r#"Mapped "{prev_path}" with id "{prev_id}" to "{path}" with id "{}""#,
             result.last_insert_id
@@ -1,4 +1,54 @@
 pub mod crates;
+pub mod cues;
 pub mod directories;
+pub mod itunes;
 pub mod locations;
+pub mod playlists;
+pub mod report;
+pub mod rhythmbox;
 pub mod tracks;
+
+use sea_orm::DbErr;
+use std::future::Future;
+
+/// How to handle an incoming item whose name is already taken in the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NameConflict {
+    /// Combine the incoming tracks into the existing item
+    Union,
+    /// Create a new item with a numbered suffix
+    Rename,
+    /// Leave the existing item alone and drop the incoming one
+    Skip,
+}
+
+/// First of `name (2)`, `name (3)` and so on that is not taken yet
+pub async fn get_unused_name<F, Fut>(name: &str, is_taken: F) -> Result<String, DbErr>
+where
+    F: Fn(String) -> Fut,
+    Fut: Future<Output = Result<bool, DbErr>>,
+{
+    let mut suffix = 2;
+    loop {
+        let candidate = format!("{name} ({suffix})");
+        if !is_taken(candidate.clone()).await? {
+            return Ok(candidate);
+        }
+        suffix += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+
+    #[test]
+    fn picks_first_free_suffix() {
+        let taken = ["Set (2)", "Set (3)"];
+        let name = block_on(get_unused_name("Set", |candidate| async move {
+            Ok(taken.contains(&candidate.as_str()))
+        }));
+        assert_eq!(name.unwrap(), "Set (4)");
+    }
+}
@@ -0,0 +1,315 @@
+use super::report::{Outcome, Report, Section};
+use super::{get_unused_name, tracks::TrackMapping, NameConflict};
+use crate::database::schema::{playlist_tracks, playlists};
+use log::{debug, warn};
+use sea_orm::sea_query::{Expr, InsertStatement, Query, SimpleExpr};
+use sea_orm::{
+    ActiveValue, ColumnTrait, ConnectionTrait, DbErr, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, QueryTrait,
+};
+use std::{collections::HashMap, hash::BuildHasher};
+
+/// Regular playlist shown in the sidebar
+pub const HIDDEN_NONE: i32 = 0;
+/// The Auto DJ queue, of which Mixxx expects exactly one
+pub const HIDDEN_AUTO_DJ: i32 = 1;
+/// A history playlist recorded by the set log
+pub const HIDDEN_SET_LOG: i32 = 2;
+
+/// How to handle Mixxx's special hidden playlists
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HiddenPlaylists {
+    /// Leave out the Auto DJ queue and set log history
+    Skip,
+    /// Append the Auto DJ queue to the target queue and copy over set log history
+    Merge,
+}
+
+pub async fn get<C: ConnectionTrait>(db: &C) -> Result<Vec<playlists::Model>, DbErr> {
+    playlists::Entity::find()
+        .order_by_asc(playlists::Column::Position)
+        .all(db)
+        .await
+}
+
+pub async fn get_tracks<C: ConnectionTrait>(db: &C) -> Result<Vec<playlist_tracks::Model>, DbErr> {
+    playlist_tracks::Entity::find()
+        .order_by_asc(playlist_tracks::Column::PlaylistId)
+        .order_by_asc(playlist_tracks::Column::Position)
+        .all(db)
+        .await
+}
+
+pub async fn get_by_name<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+) -> Result<Option<playlists::Model>, DbErr> {
+    playlists::Entity::find()
+        .filter(playlists::Column::Name.eq(name))
+        .filter(playlists::Column::Hidden.eq(HIDDEN_NONE))
+        .one(db)
+        .await
+}
+
+/// Find a regular playlist by name or create it at the end of the sidebar
+pub async fn get_by_name_or_create<C: ConnectionTrait>(
+    db: &C,
+    name: &str,
+) -> Result<playlists::Model, DbErr> {
+    if let Some(found) = get_by_name(db, name).await? {
+        debug!(r#"Found playlist "{name}" with id "{}""#, found.id);
+        return Ok(found);
+    }
+    let position = get_next_position(db).await?;
+    let stmt = Query::insert()
+        .into_table(playlists::Entity)
+        .columns([
+            playlists::Column::Name,
+            playlists::Column::Position,
+            playlists::Column::Hidden,
+            playlists::Column::DateCreated,
+            playlists::Column::DateModified,
+            playlists::Column::Locked,
+        ])
+        .values_panic([
+            name.into(),
+            position.into(),
+            HIDDEN_NONE.into(),
+            Expr::cust("CURRENT_TIMESTAMP"),
+            Expr::cust("CURRENT_TIMESTAMP"),
+            0.into(),
+        ])
+        .to_owned();
+    let id = insert_playlist(db, &stmt).await?;
+    debug!(r#"Created playlist "{name}" with id "{id}""#);
+    playlists::Entity::find_by_id(id)
+        .one(db)
+        .await?
+        .ok_or_else(|| DbErr::RecordNotFound(format!(r#"Playlist "{name}""#)))
+}
+
+pub async fn clear_tracks<C: ConnectionTrait>(db: &C, playlist_id: i32) -> Result<(), DbErr> {
+    playlist_tracks::Entity::delete_many()
+        .filter(playlist_tracks::Column::PlaylistId.eq(playlist_id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+pub async fn get_auto_dj<C: ConnectionTrait>(db: &C) -> Result<Option<playlists::Model>, DbErr> {
+    playlists::Entity::find()
+        .filter(playlists::Column::Hidden.eq(HIDDEN_AUTO_DJ))
+        .one(db)
+        .await
+}
+
+pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
+    db: &C,
+    playlists: Vec<playlists::Model>,
+    playlist_tracks: Vec<playlist_tracks::Model>,
+    track_map: &HashMap<i32, TrackMapping, S>,
+    conflict: NameConflict,
+    hidden: HiddenPlaylists,
+    report: &mut Report,
+) -> Result<(), DbErr> {
+    let mut playlist_map = HashMap::with_capacity(playlists.len());
+    for source in playlists {
+        let prev_id = source.id;
+        let name = source.name.clone().unwrap_or_default();
+        let subject = format!(r#""{name}""#);
+        let (mapped_id, outcome, reason) = match (source.hidden, hidden) {
+            (HIDDEN_AUTO_DJ | HIDDEN_SET_LOG, HiddenPlaylists::Skip) => {
+                debug!(r#"Skipping hidden playlist "{name}""#);
+                report.record(Section::Playlists, Outcome::Skipped, subject, "hidden");
+                continue;
+            }
+            (HIDDEN_AUTO_DJ, HiddenPlaylists::Merge) => match get_auto_dj(db).await? {
+                Some(found) => {
+                    debug!(r#"Appending Auto DJ queue to playlist id "{}""#, found.id);
+                    (
+                        found.id,
+                        Outcome::Conflicted,
+                        "appended to Auto DJ queue".into(),
+                    )
+                }
+                None => {
+                    let id = create_from(db, source, &name).await?;
+                    (id, Outcome::Added, "new Auto DJ queue".into())
+                }
+            },
+            (HIDDEN_SET_LOG, HiddenPlaylists::Merge) => {
+                let id = create_from(db, source, &name).await?;
+                (id, Outcome::Added, "history".into())
+            }
+            _ => match (get_by_name(db, &name).await?, conflict) {
+                (None, _) => {
+                    let id = create_from(db, source, &name).await?;
+                    (id, Outcome::Added, "new".into())
+                }
+                (Some(found), NameConflict::Union) => {
+                    debug!(
+                        r#"Appending playlist "{name}" to existing playlist id "{}""#,
+                        found.id
+                    );
+                    (
+                        found.id,
+                        Outcome::Conflicted,
+                        "appended to existing playlist".into(),
+                    )
+                }
+                (Some(_), NameConflict::Rename) => {
+                    let renamed = get_unused_name(&name, |candidate| async move {
+                        Ok(get_by_name(db, &candidate).await?.is_some())
+                    })
+                    .await?;
+                    let id = create_from(db, source, &renamed).await?;
+                    (id, Outcome::Remapped, format!(r#"renamed to "{renamed}""#))
+                }
+                (Some(_), NameConflict::Skip) => {
+                    warn!(r#"Playlist "{name}" already exists! Skipping..."#);
+                    report.record(
+                        Section::Playlists,
+                        Outcome::Skipped,
+                        subject,
+                        "name is taken",
+                    );
+                    continue;
+                }
+            },
+        };
+        report.record(Section::Playlists, outcome, subject, reason);
+        playlist_map.insert(prev_id, mapped_id);
+    }
+
+    insert_entries(db, playlist_tracks, &playlist_map, track_map, report).await
+}
+
+async fn insert_entries<C: ConnectionTrait, S: BuildHasher>(
+    db: &C,
+    playlist_tracks: Vec<playlist_tracks::Model>,
+    playlist_map: &HashMap<i32, i32>,
+    track_map: &HashMap<i32, TrackMapping, S>,
+    report: &mut Report,
+) -> Result<(), DbErr> {
+    let mut offsets = HashMap::<i32, i32>::with_capacity(playlist_map.len());
+    for entry in playlist_tracks {
+        let mapped = entry
+            .playlist_id
+            .and_then(|id| playlist_map.get(&id))
+            .zip(entry.track_id.and_then(|id| track_map.get(&id)));
+        let Some((playlist_id, track_id)) = mapped.map(|(playlist, track)| (playlist, track.id()))
+        else {
+            debug!(
+                r#"Could not map playlist entry with id "{}"! Skipping..."#,
+                entry.id
+            );
+            report.record(
+                Section::Playlists,
+                Outcome::Skipped,
+                format!(
+                    r#"Track id "{}" in playlist id "{}""#,
+                    entry.track_id.unwrap_or_default(),
+                    entry.playlist_id.unwrap_or_default()
+                ),
+                "track or playlist was not merged",
+            );
+            continue;
+        };
+        let offset = match offsets.get(playlist_id) {
+            Some(offset) => *offset,
+            None => {
+                let offset = get_last_position(db, *playlist_id).await?;
+                offsets.insert(*playlist_id, offset);
+                offset
+            }
+        };
+        let position = offset + entry.position.unwrap_or_default();
+        append_track(
+            db,
+            *playlist_id,
+            track_id,
+            position,
+            entry.pl_datetime_added,
+        )
+        .await?;
+    }
+    Ok(())
+}
+
+/// Insert a track into a playlist, storing `pl_datetime_added` as text because
+/// Mixxx cannot read the timestamp back if it is saved as a blob
+pub async fn append_track<C: ConnectionTrait>(
+    db: &C,
+    playlist_id: i32,
+    track_id: i32,
+    position: i32,
+    datetime_added: Option<Vec<u8>>,
+) -> Result<(), DbErr> {
+    let added: SimpleExpr = match datetime_added {
+        Some(bytes) => String::from_utf8_lossy(&bytes).into_owned().into(),
+        None => Expr::cust("CURRENT_TIMESTAMP"),
+    };
+    let stmt = Query::insert()
+        .into_table(playlist_tracks::Entity)
+        .columns([
+            playlist_tracks::Column::PlaylistId,
+            playlist_tracks::Column::TrackId,
+            playlist_tracks::Column::Position,
+            playlist_tracks::Column::PlDatetimeAdded,
+        ])
+        .values_panic([playlist_id.into(), track_id.into(), position.into(), added])
+        .to_owned();
+    db.execute(db.get_database_backend().build(&stmt)).await?;
+    debug!(r#"Added track id "{track_id}" to playlist id "{playlist_id}" at position {position}"#);
+    Ok(())
+}
+
+async fn get_last_position<C: ConnectionTrait>(db: &C, playlist_id: i32) -> Result<i32, DbErr> {
+    let last = playlist_tracks::Entity::find()
+        .select_only()
+        .column_as(playlist_tracks::Column::Position.max(), "position")
+        .filter(playlist_tracks::Column::PlaylistId.eq(playlist_id))
+        .into_tuple::<Option<i32>>()
+        .one(db)
+        .await?;
+    Ok(last.flatten().unwrap_or_default())
+}
+
+async fn get_next_position<C: ConnectionTrait>(db: &C) -> Result<i32, DbErr> {
+    let last = playlists::Entity::find()
+        .select_only()
+        .column_as(playlists::Column::Position.max(), "position")
+        .into_tuple::<Option<i32>>()
+        .one(db)
+        .await?;
+    Ok(last.flatten().unwrap_or_default() + 1)
+}
+
+/// `Playlists.id` is an `INTEGER PRIMARY KEY` and so an alias of the rowid,
+/// which `SQLite` assigns when the id is left out
+async fn insert_playlist<C: ConnectionTrait>(db: &C, stmt: &InsertStatement) -> Result<i32, DbErr> {
+    let result = db.execute(db.get_database_backend().build(stmt)).await?;
+    i32::try_from(result.last_insert_id()).map_err(|_| {
+        DbErr::Custom(format!(
+            "Playlist id {} out of range",
+            result.last_insert_id()
+        ))
+    })
+}
+
+async fn create_from<C: ConnectionTrait>(
+    db: &C,
+    source: playlists::Model,
+    name: &str,
+) -> Result<i32, DbErr> {
+    let position = get_next_position(db).await?;
+    let data = playlists::ActiveModel {
+        id: ActiveValue::NotSet,
+        name: ActiveValue::Set(Some(name.to_owned())),
+        position: ActiveValue::Set(Some(position)),
+        ..source.into_active_model()
+    };
+    let id = insert_playlist(db, &playlists::Entity::insert(data).into_query()).await?;
+    debug!(r#"Created playlist "{name}" with id "{id}""#);
+    Ok(id)
+}
@@ -0,0 +1,139 @@
+use std::fmt::{self, Display, Formatter};
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+/// Kind of row that a merge step operates on
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, EnumIter)]
+pub enum Section {
+    Directories,
+    Locations,
+    Tracks,
+    Cues,
+    Crates,
+    Playlists,
+}
+
+/// What happened to a single source row
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Display, EnumIter)]
+pub enum Outcome {
+    /// Inserted as a new row
+    Added,
+    /// Inserted or matched under a different identity than in the source
+    Remapped,
+    /// Matched an existing row and resolved through a conflict policy
+    Conflicted,
+    /// Left out of the target
+    Skipped,
+}
+
+#[derive(Debug)]
+pub struct Entry {
+    pub section: Section,
+    pub outcome: Outcome,
+    pub subject: String,
+    pub reason: String,
+}
+
+/// Record of every change made by a merge, grouped by section and outcome
+#[derive(Debug, Default)]
+pub struct Report {
+    entries: Vec<Entry>,
+}
+
+impl Report {
+    pub fn record(
+        &mut self,
+        section: Section,
+        outcome: Outcome,
+        subject: impl Into<String>,
+        reason: impl Into<String>,
+    ) {
+        self.entries.push(Entry {
+            section,
+            outcome,
+            subject: subject.into(),
+            reason: reason.into(),
+        });
+    }
+
+    pub fn count(&self, section: Section, outcome: Outcome) -> usize {
+        self.entries
+            .iter()
+            .filter(|entry| entry.section == section && entry.outcome == outcome)
+            .count()
+    }
+
+    /// Counts of each outcome per section without the individual entries
+    pub fn summary(&self) -> Summary<'_> {
+        Summary(self)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for section in Section::iter() {
+            for outcome in Outcome::iter() {
+                let mut entries = self
+                    .entries
+                    .iter()
+                    .filter(|entry| entry.section == section && entry.outcome == outcome)
+                    .peekable();
+                if entries.peek().is_none() {
+                    continue;
+                }
+                writeln!(f, "{section} {}:", outcome.to_string().to_lowercase())?;
+                for entry in entries {
+                    writeln!(f, "  {} ({})", entry.subject, entry.reason)?;
+                }
+            }
+        }
+        write!(f, "{}", self.summary())
+    }
+}
+
+pub struct Summary<'a>(&'a Report);
+
+impl Display for Summary<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:<12}", "")?;
+        for outcome in Outcome::iter() {
+            write!(f, "{:>12}", outcome.to_string())?;
+        }
+        for section in Section::iter() {
+            write!(f, "\n{:<12}", section.to_string())?;
+            for outcome in Outcome::iter() {
+                write!(f, "{:>12}", self.0.count(section, outcome))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_by_section_and_outcome() {
+        let mut report = Report::default();
+        report.record(Section::Tracks, Outcome::Added, "a", "new");
+        report.record(Section::Tracks, Outcome::Added, "b", "new");
+        report.record(Section::Tracks, Outcome::Skipped, "c", "missing");
+        report.record(Section::Cues, Outcome::Added, "d", "new");
+        assert_eq!(report.count(Section::Tracks, Outcome::Added), 2);
+        assert_eq!(report.count(Section::Tracks, Outcome::Skipped), 1);
+        assert_eq!(report.count(Section::Crates, Outcome::Added), 0);
+    }
+
+    #[test]
+    fn lists_entries_under_headings() {
+        let mut report = Report::default();
+        report.record(
+            Section::Crates,
+            Outcome::Skipped,
+            r#""Warmup""#,
+            "name taken",
+        );
+        let output = report.to_string();
+        assert!(output.contains("Crates skipped:\n  \"Warmup\" (name taken)\n"));
+    }
+}
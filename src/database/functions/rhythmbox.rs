@@ -0,0 +1,27 @@
+//! The Rhythmbox library that Mixxx caches when it is enabled as an external library
+
+use crate::database::schema::{rhythmbox_library, rhythmbox_playlist_tracks, rhythmbox_playlists};
+use sea_orm::{ConnectionTrait, DbErr, EntityTrait, QueryOrder};
+
+pub async fn get<C: ConnectionTrait>(db: &C) -> Result<Vec<rhythmbox_library::Model>, DbErr> {
+    rhythmbox_library::Entity::find().all(db).await
+}
+
+pub async fn get_playlists<C: ConnectionTrait>(
+    db: &C,
+) -> Result<Vec<rhythmbox_playlists::Model>, DbErr> {
+    rhythmbox_playlists::Entity::find()
+        .order_by_asc(rhythmbox_playlists::Column::Id)
+        .all(db)
+        .await
+}
+
+pub async fn get_playlist_tracks<C: ConnectionTrait>(
+    db: &C,
+) -> Result<Vec<rhythmbox_playlist_tracks::Model>, DbErr> {
+    rhythmbox_playlist_tracks::Entity::find()
+        .order_by_asc(rhythmbox_playlist_tracks::Column::PlaylistId)
+        .order_by_asc(rhythmbox_playlist_tracks::Column::Position)
+        .all(db)
+        .await
+}
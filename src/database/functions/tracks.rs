@@ -1,9 +1,11 @@
+use super::report::{Outcome, Report, Section};
 use crate::database::schema::{library, track_locations};
 use log::{debug, warn};
-use sea_orm::sea_query::SqliteQueryBuilder;
+use sea_orm::prelude::DateTime;
+use sea_orm::sea_query::{Expr, Func, SimpleExpr, SqliteQueryBuilder};
 use sea_orm::{
-    ActiveValue, ColumnTrait, ConnectionTrait, DatabaseBackend, DbErr, EntityTrait,
-    FromQueryResult, QueryFilter, QueryTrait, Statement,
+    ActiveModelTrait, ActiveValue, ColumnTrait, ConnectionTrait, DatabaseBackend, DbErr,
+    EntityTrait, FromQueryResult, IntoActiveModel, QueryFilter, QuerySelect, QueryTrait, Statement,
 };
 use std::collections::HashMap;
 use std::hash::BuildHasher;
@@ -42,37 +44,398 @@ pub async fn get_by_location<C: ConnectionTrait>(
         .await
 }
 
+pub async fn get_by_location_id<C: ConnectionTrait>(
+    db: &C,
+    location_id: i32,
+) -> Result<Option<library::Model>, DbErr> {
+    library::Entity::find()
+        .filter(library::Column::Location.eq(location_id))
+        .one(db)
+        .await
+}
+
+/// Set a single column of a track without reading back the rest of the row
+pub async fn set_column<C: ConnectionTrait>(
+    db: &C,
+    id: i32,
+    column: library::Column,
+    value: impl Into<SimpleExpr>,
+) -> Result<(), DbErr> {
+    library::Entity::update_many()
+        .col_expr(column, value.into())
+        .filter(library::Column::Id.eq(id))
+        .exec(db)
+        .await?;
+    Ok(())
+}
+
+/// Seconds a track may differ from the expected duration and still match by tags
+const DURATION_TOLERANCE: f64 = 3.0;
+
+/// Find a track by artist and title ignoring case, picking the one closest to
+/// `duration` when it is known and otherwise only a single match by both tags
+pub async fn find_by_tags<C: ConnectionTrait>(
+    db: &C,
+    artist: Option<&str>,
+    title: &str,
+    duration: Option<f64>,
+) -> Result<Option<i32>, DbErr> {
+    let lower = |col: library::Column, val: &str| {
+        Expr::expr(Func::lower(Expr::col(col))).eq(Func::lower(Expr::val(val)))
+    };
+    let mut query = library::Entity::find()
+        .select_only()
+        .column(library::Column::Id)
+        .column(library::Column::Duration)
+        .filter(lower(library::Column::Title, title))
+        .filter(library::Column::MixxxDeleted.eq(0));
+    if let Some(artist) = artist {
+        query = query.filter(lower(library::Column::Artist, artist));
+    }
+    let candidates = query.into_tuple::<(i32, Option<f64>)>().all(db).await?;
+    Ok(pick_closest(&candidates, duration, artist.is_some()))
+}
+
+fn pick_closest(
+    candidates: &[(i32, Option<f64>)],
+    duration: Option<f64>,
+    has_artist: bool,
+) -> Option<i32> {
+    let Some(expected) = duration else {
+        // A title alone or several tracks with the same tags are too ambiguous
+        return match candidates {
+            [(id, _)] if has_artist => Some(*id),
+            _ => None,
+        };
+    };
+    candidates
+        .iter()
+        .filter_map(|(id, found)| Some((*id, (found.as_ref()? - expected).abs())))
+        .filter(|(_, diff)| *diff <= DURATION_TOLERANCE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id)
+}
+
+/// How to resolve a source track whose file already exists in the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackConflict {
+    /// Leave the target track untouched
+    KeepTarget,
+    /// Overwrite the target track with the source track
+    TakeSource,
+    /// Keep whichever track was most recently synchronized with its file
+    Newest,
+    /// Fill in fields that are empty in the target track from the source track
+    FillNulls,
+}
+
+/// Where a source track ended up in the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrackMapping {
+    /// Inserted as a new track
+    Inserted(i32),
+    /// Matched an existing track which was overwritten by the source
+    Replaced(i32),
+    /// Matched an existing track whose empty fields were filled by the source
+    Filled(i32),
+    /// Matched an existing track which was left untouched
+    Kept(i32),
+}
+
+impl TrackMapping {
+    pub fn id(self) -> i32 {
+        match self {
+            Self::Inserted(id) | Self::Replaced(id) | Self::Filled(id) | Self::Kept(id) => id,
+        }
+    }
+}
+
 pub async fn insert<C: ConnectionTrait, S: BuildHasher>(
     db: &C,
     tracks: Vec<library::Model>,
     location_map: &HashMap<i32, i32, S>,
-) -> Result<(), DbErr> {
+    conflict: TrackConflict,
+    report: &mut Report,
+) -> Result<HashMap<i32, TrackMapping>, DbErr> {
+    let mut track_map = HashMap::with_capacity(tracks.len());
     for track in tracks {
+        let prev_id = track.id;
         let display = format!(
             r#""{} - {}""#,
             &track.artist.as_deref().unwrap_or("<N/A>"),
             &track.title.as_deref().unwrap_or("<N/A>")
         );
         let Some(prev_loc_id) = track.location else {
-            warn!(r#"Track {display} has no original location! Skipping..."#);
+            warn!("Track {display} has no original location! Skipping...");
+            report.record(Section::Tracks, Outcome::Skipped, display, "no location");
             continue;
         };
         let Some(mapped_loc_id) = location_map.get(&prev_loc_id) else {
             warn!(
                 r#"Could not find new location of {display} with id "{prev_loc_id}"! Skipping..."#,
             );
+            report.record(
+                Section::Tracks,
+                Outcome::Skipped,
+                display,
+                format!(r#"location id "{prev_loc_id}" was not merged"#),
+            );
             continue;
         };
+        if let Some(existing) = get_by_location_id(db, *mapped_loc_id).await? {
+            let mapping = Box::pin(resolve_conflict(db, existing, track, conflict)).await?;
+            debug!("Resolved {display} to existing track as {mapping:?}");
+            let reason = match mapping {
+                TrackMapping::Replaced(_) => "replaced by source",
+                TrackMapping::Filled(_) => "filled from source",
+                TrackMapping::Inserted(_) | TrackMapping::Kept(_) => "kept target",
+            };
+            report.record(
+                Section::Tracks,
+                Outcome::Conflicted,
+                display,
+                format!(r#"{reason} as track id "{}""#, mapping.id()),
+            );
+            track_map.insert(prev_id, mapping);
+            continue;
+        }
         let input = library::ActiveModel {
             id: ActiveValue::NotSet,
             location: ActiveValue::Set(Some(*mapped_loc_id)),
             ..track.into()
         };
         let result = library::Entity::insert(input).exec(db).await?;
+        fix_datetime_added(db, result.last_insert_id).await?;
+        track_map.insert(prev_id, TrackMapping::Inserted(result.last_insert_id));
         debug!(
             r#"Created {display} with track id {}, mapping location id from "{prev_loc_id}" to "{mapped_loc_id}""#,
             result.last_insert_id
         );
+        report.record(
+            Section::Tracks,
+            Outcome::Added,
+            display,
+            format!(r#"new track id "{}""#, result.last_insert_id),
+        );
+    }
+    Ok(track_map)
+}
+
+/// Whether `track` was synchronized with its file more recently than `other`,
+/// tracks that never were counting as oldest
+fn is_newer(track: &library::Model, other: &library::Model) -> bool {
+    track.source_synchronized_ms > other.source_synchronized_ms
+}
+
+async fn resolve_conflict<C: ConnectionTrait>(
+    db: &C,
+    target: library::Model,
+    source: library::Model,
+    conflict: TrackConflict,
+) -> Result<TrackMapping, DbErr> {
+    let id = target.id;
+    let take_source = match conflict {
+        TrackConflict::KeepTarget => false,
+        TrackConflict::TakeSource => true,
+        TrackConflict::Newest => is_newer(&source, &target),
+        TrackConflict::FillNulls => {
+            update(db, fill_nulls(target, source)).await?;
+            return Ok(TrackMapping::Filled(id));
+        }
+    };
+    if !take_source {
+        return Ok(TrackMapping::Kept(id));
     }
+    let replacement = library::Model {
+        id,
+        location: target.location,
+        datetime_added: target.datetime_added,
+        mixxx_deleted: target.mixxx_deleted,
+        ..source
+    };
+    update(db, replacement).await?;
+    Ok(TrackMapping::Replaced(id))
+}
+
+async fn update<C: ConnectionTrait>(db: &C, model: library::Model) -> Result<(), DbErr> {
+    let id = model.id;
+    let data = library::ActiveModel {
+        id: ActiveValue::Unchanged(id),
+        ..model.into_active_model().reset_all()
+    };
+    library::Entity::update(data).exec(db).await?;
+    fix_datetime_added(db, id).await
+}
+
+/// Blob columns are written back as blobs, but Mixxx can only parse
+/// `datetime_added` when it is stored as text
+async fn fix_datetime_added<C: ConnectionTrait>(db: &C, id: i32) -> Result<(), DbErr> {
+    library::Entity::update_many()
+        .col_expr(
+            library::Column::DatetimeAdded,
+            Expr::cust("CAST(\"datetime_added\" AS TEXT)"),
+        )
+        .filter(library::Column::Id.eq(id))
+        .exec(db)
+        .await?;
     Ok(())
 }
+
+trait IsBlank {
+    fn is_blank(&self) -> bool;
+}
+
+impl IsBlank for Option<String> {
+    fn is_blank(&self) -> bool {
+        self.as_deref().is_none_or(str::is_empty)
+    }
+}
+
+impl IsBlank for Option<i32> {
+    fn is_blank(&self) -> bool {
+        self.is_none_or(|val| val == 0)
+    }
+}
+
+impl IsBlank for Option<f64> {
+    fn is_blank(&self) -> bool {
+        self.is_none_or(|val| val == 0.0)
+    }
+}
+
+impl IsBlank for Option<Vec<u8>> {
+    fn is_blank(&self) -> bool {
+        self.as_ref().is_none_or(Vec::is_empty)
+    }
+}
+
+impl IsBlank for Option<DateTime> {
+    fn is_blank(&self) -> bool {
+        self.is_none()
+    }
+}
+
+macro_rules! fill {
+    ($target:ident, $source:ident, $($field:ident),+ $(,)?) => {
+        $(
+            if $target.$field.is_blank() {
+                $target.$field = $source.$field;
+            }
+        )+
+    };
+}
+
+/// Fill in blank fields of the target from the source, keeping related fields
+/// such as beats and their version together
+fn fill_nulls(mut target: library::Model, source: library::Model) -> library::Model {
+    fill!(
+        target,
+        source,
+        artist,
+        title,
+        album,
+        year,
+        genre,
+        tracknumber,
+        tracktotal,
+        comment,
+        url,
+        composer,
+        grouping,
+        album_artist,
+        duration,
+        bitrate,
+        samplerate,
+        channels,
+        cuepoint,
+        replaygain,
+        replaygain_peak,
+        rating,
+        timesplayed,
+        played,
+        last_played_at,
+        color,
+    );
+    if target.beats.is_blank() {
+        target.beats = source.beats;
+        target.beats_version = source.beats_version;
+        target.beats_sub_version = source.beats_sub_version;
+        target.bpm = source.bpm;
+        target.bpm_lock = source.bpm_lock;
+    }
+    if target.keys.is_blank() {
+        target.keys = source.keys;
+        target.keys_version = source.keys_version;
+        target.keys_sub_version = source.keys_sub_version;
+        target.key = source.key;
+        target.key_id = source.key_id;
+    }
+    target
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_blank_fields() {
+        let target = library::Model {
+            artist: Some("Target".into()),
+            title: Some(String::new()),
+            rating: Some(0),
+            ..Default::default()
+        };
+        let source = library::Model {
+            artist: Some("Source".into()),
+            title: Some("Title".into()),
+            rating: Some(4),
+            ..Default::default()
+        };
+        let result = fill_nulls(target, source);
+        assert_eq!(result.artist.as_deref(), Some("Target"));
+        assert_eq!(result.title.as_deref(), Some("Title"));
+        assert_eq!(result.rating, Some(4));
+    }
+
+    #[test]
+    fn fills_beats_together() {
+        let target = library::Model {
+            bpm: Some(120.0),
+            ..Default::default()
+        };
+        let source = library::Model {
+            bpm: Some(128.0),
+            beats: Some(vec![1, 2, 3]),
+            beats_version: Some("BeatGrid-2.0".into()),
+            ..Default::default()
+        };
+        let result = fill_nulls(target, source);
+        assert_eq!(result.bpm, Some(128.0));
+        assert_eq!(result.beats_version.as_deref(), Some("BeatGrid-2.0"));
+    }
+
+    #[test]
+    fn compares_millisecond_timestamps() {
+        let older = library::Model {
+            source_synchronized_ms: Some(1_700_000_000_000),
+            ..Default::default()
+        };
+        let newer = library::Model {
+            source_synchronized_ms: Some(1_700_000_000_001),
+            ..Default::default()
+        };
+        assert!(is_newer(&newer, &older));
+        assert!(!is_newer(&older, &newer));
+        assert!(is_newer(&older, &library::Model::default()));
+    }
+
+    #[test]
+    fn picks_closest_duration() {
+        let candidates = [(1, Some(200.0)), (2, Some(181.0)), (3, None)];
+        assert_eq!(pick_closest(&candidates, Some(180.0), true), Some(2));
+        assert_eq!(pick_closest(&candidates, Some(240.0), true), None);
+        assert_eq!(pick_closest(&candidates, None, true), None);
+        assert_eq!(pick_closest(&candidates[..1], None, true), Some(1));
+        assert_eq!(pick_closest(&candidates[..1], None, false), None);
+    }
+}
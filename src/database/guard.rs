@@ -0,0 +1,114 @@
+use log::debug;
+use sea_orm::{ConnectOptions, ConnectionTrait, Database, DbErr};
+use std::fmt::{self, Display, Formatter};
+use std::path::{Path, PathBuf};
+
+/// Sign that Mixxx currently has a database open
+#[derive(Debug, PartialEq, Eq)]
+pub enum Usage {
+    /// A Mixxx process was found in the process table
+    Process(u32),
+    /// Another connection holds a lock on the database
+    Locked,
+    /// The database has an uncheckpointed write-ahead log
+    Wal,
+}
+
+impl Display for Usage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Process(pid) => write!(f, r#"Mixxx is running with process id "{pid}""#),
+            Self::Locked => write!(f, "the database is locked by another program"),
+            Self::Wal => write!(f, "the database has a write-ahead log in use"),
+        }
+    }
+}
+
+/// Check whether Mixxx is running, falling back to probing the database itself
+/// where the process table cannot be read
+pub async fn detect(database: &Path) -> Result<Option<Usage>, DbErr> {
+    if let Some(pid) = find_process() {
+        return Ok(Some(Usage::Process(pid)));
+    }
+    if !database.exists() {
+        return Ok(None);
+    }
+    if get_side_file(database, "-wal")
+        .metadata()
+        .is_ok_and(|meta| meta.len() > 0)
+    {
+        return Ok(Some(Usage::Wal));
+    }
+    match is_locked(database).await? {
+        true => Ok(Some(Usage::Locked)),
+        false => Ok(None),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn find_process() -> Option<u32> {
+    let entries = std::fs::read_dir("/proc").ok()?;
+    entries.flatten().find_map(|entry| {
+        let pid = entry.file_name().to_str()?.parse::<u32>().ok()?;
+        let stat = std::fs::read_to_string(entry.path().join("stat")).ok()?;
+        match is_live_mixxx(&stat) {
+            true => {
+                debug!(r#"Found Mixxx process with id "{pid}""#);
+                Some(pid)
+            }
+            false => None,
+        }
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn find_process() -> Option<u32> {
+    None
+}
+
+/// Whether a `/proc/<pid>/stat` line belongs to a Mixxx process that has not exited
+fn is_live_mixxx(stat: &str) -> bool {
+    let (Some(open), Some(close)) = (stat.find('('), stat.rfind(')')) else {
+        return false;
+    };
+    let state = stat[close + 1..].trim_start().chars().next();
+    stat[open + 1..close].eq_ignore_ascii_case("mixxx") && !matches!(state, Some('Z' | 'X'))
+}
+
+fn get_side_file(database: &Path, suffix: &str) -> PathBuf {
+    let mut name = database.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Try to take a write lock without waiting for it
+async fn is_locked(database: &Path) -> Result<bool, DbErr> {
+    let url = String::from("sqlite://") + &database.to_string_lossy();
+    let db = Database::connect(ConnectOptions::new(url).max_connections(1).to_owned()).await?;
+    db.execute_unprepared("PRAGMA busy_timeout = 0").await?;
+    let locked = match db.execute_unprepared("BEGIN IMMEDIATE").await {
+        Ok(_) => {
+            db.execute_unprepared("ROLLBACK").await?;
+            false
+        }
+        Err(err) if err.to_string().contains("database is locked") => true,
+        Err(err) => return Err(err),
+    };
+    db.close().await?;
+    debug!(r#"Probed lock on "{}": {locked}"#, database.display());
+    Ok(locked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_live_mixxx_process() {
+        assert!(is_live_mixxx("1234 (mixxx) S 1 1234 1234 0 -1\n"));
+        assert!(is_live_mixxx("1234 (Mixxx) R 1 1234 1234 0 -1\n"));
+        assert!(!is_live_mixxx("1234 (mixxx) Z 1 1234 1234 0 -1\n"));
+        assert!(!is_live_mixxx("1234 (mixxxkit) S 1 1234 1234 0 -1\n"));
+        assert!(!is_live_mixxx("1234 (a) (mixxx) S 1\n"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Musical keys as Mixxx numbers them in `key_id`, along with the notations
+//! that taggers write into the `key` column
+
+/// Names of the pitch classes from C, preferring flats as Mixxx does except
+/// for F sharp and the minor keys commonly written with sharps
+const MAJOR_NAMES: [&str; 12] = [
+    "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+];
+const MINOR_NAMES: [&str; 12] = [
+    "C", "C#", "D", "Eb", "E", "F", "F#", "G", "G#", "A", "Bb", "B",
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Notation {
+    /// Also known as Camelot, from 1A to 12B
+    Lancelot,
+    OpenKey,
+    Traditional,
+}
+
+/// Key numbered from 1 for C major to 12 for B major and from 13 for C minor
+/// to 24 for B minor
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key(i32);
+
+impl Key {
+    pub fn from_id(id: i32) -> Option<Key> {
+        (1..=24).contains(&id).then_some(Key(id))
+    }
+
+    pub fn id(self) -> i32 {
+        self.0
+    }
+
+    /// Parse Lancelot, Open Key or traditional notation such as `8A`, `1m`,
+    /// `Am`, `A minor` or `F♯/G♭`
+    pub fn parse(text: &str) -> Option<Key> {
+        let text = text.trim();
+        let (number, mode) = text.split_at(text.len() - text.chars().last()?.len_utf8());
+        if let Ok(number) = number.parse::<i32>() {
+            if !(1..=12).contains(&number) {
+                return None;
+            }
+            return match mode {
+                "A" | "a" => Some(Key::from_lancelot(number, true)),
+                "B" | "b" => Some(Key::from_lancelot(number, false)),
+                "M" | "m" => Some(Key::from_lancelot(number + 7, true)),
+                "D" | "d" => Some(Key::from_lancelot(number + 7, false)),
+                _ => None,
+            };
+        }
+        parse_traditional(text.split('/').next()?)
+    }
+
+    pub fn format(self, notation: Notation) -> String {
+        let (lancelot, minor) = (self.get_lancelot(), self.is_minor());
+        let pitch = usize::try_from(self.get_pitch()).unwrap_or_default();
+        match notation {
+            Notation::Lancelot => format!("{lancelot}{}", if minor { 'A' } else { 'B' }),
+            Notation::OpenKey => {
+                let number = (lancelot - 8).rem_euclid(12) + 1;
+                format!("{number}{}", if minor { 'm' } else { 'd' })
+            }
+            Notation::Traditional => match minor {
+                true => format!("{}m", MINOR_NAMES[pitch]),
+                false => MAJOR_NAMES[pitch].to_owned(),
+            },
+        }
+    }
+
+    fn from_pitch(pitch: i32, minor: bool) -> Key {
+        Key(pitch.rem_euclid(12) + if minor { 13 } else { 1 })
+    }
+
+    /// Lancelot numbers go around the circle of fifths with 8 being C major
+    /// and its relative minor, A minor
+    fn from_lancelot(number: i32, minor: bool) -> Key {
+        let major = (number - 8) * 7;
+        match minor {
+            true => Key::from_pitch(major - 3, true),
+            false => Key::from_pitch(major, false),
+        }
+    }
+
+    fn get_lancelot(self) -> i32 {
+        let major = match self.is_minor() {
+            true => self.get_pitch() + 3,
+            false => self.get_pitch(),
+        };
+        (major * 7 + 7).rem_euclid(12) + 1
+    }
+
+    fn get_pitch(self) -> i32 {
+        (self.0 - 1) % 12
+    }
+
+    fn is_minor(self) -> bool {
+        self.0 > 12
+    }
+}
+
+fn parse_traditional(text: &str) -> Option<Key> {
+    let mut chars = text.trim().chars();
+    let pitch = match chars.next()?.to_ascii_uppercase() {
+        'C' => 0,
+        'D' => 2,
+        'E' => 4,
+        'F' => 5,
+        'G' => 7,
+        'A' => 9,
+        'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (pitch, rest) = match rest.chars().next() {
+        Some(accidental @ ('#' | '♯')) => (pitch + 1, &rest[accidental.len_utf8()..]),
+        Some(accidental @ ('b' | '♭')) => (pitch - 1, &rest[accidental.len_utf8()..]),
+        _ => (pitch, rest),
+    };
+    match rest.trim().to_lowercase().as_str() {
+        "" | "maj" | "major" => Some(Key::from_pitch(pitch, false)),
+        "m" | "min" | "minor" => Some(Key::from_pitch(pitch, true)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_common_notations() {
+        let a_minor = Key::from_id(22);
+        for text in ["Am", "A minor", "a min", "8A", "8a", "1m", " 1M "] {
+            assert_eq!(Key::parse(text), a_minor, "{text}");
+        }
+        let d_flat = Key::from_id(2);
+        for text in ["Db", "C#", "C♯ major", "D♭/C♯", "3B", "8d"] {
+            assert_eq!(Key::parse(text), d_flat, "{text}");
+        }
+        assert_eq!(Key::parse("Bbm"), Key::from_id(23));
+        for text in ["", "13A", "0B", "H", "Amaj7", "8C"] {
+            assert_eq!(Key::parse(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn formats_what_it_parses() {
+        for id in 1..=24 {
+            let key = Key::from_id(id).unwrap();
+            for notation in [Notation::Lancelot, Notation::OpenKey, Notation::Traditional] {
+                assert_eq!(Key::parse(&key.format(notation)), Some(key));
+            }
+        }
+        let e_minor = Key::from_id(17).unwrap();
+        assert_eq!(e_minor.format(Notation::Lancelot), "9A");
+        assert_eq!(e_minor.format(Notation::OpenKey), "2m");
+        assert_eq!(e_minor.format(Notation::Traditional), "Em");
+    }
+}
@@ -1,48 +1,39 @@
+pub mod backup;
+pub mod codec;
 pub mod functions;
+pub mod guard;
+pub mod key;
 pub mod schema;
+pub mod settings;
 
-use inquire::CustomUserError;
 use sea_orm::{
     ConnectOptions, ConnectionTrait, Database, DatabaseBackend, DatabaseConnection, DbErr,
     Statement,
 };
-use std::path::PathBuf;
+
+pub use settings::get_mixxx_database_path;
 
 pub async fn get_sqlite_connection(path: &str) -> Result<DatabaseConnection, DbErr> {
     let url = String::from("sqlite://") + path;
     Database::connect(ConnectOptions::new(url)).await
 }
 
-pub fn get_mixxx_database_path() -> Result<PathBuf, CustomUserError> {
-    Ok([get_mixxx_directory()?, "mixxxdb.sqlite".into()]
-        .into_iter()
-        .collect())
-}
-
-#[cfg(target_os = "windows")]
-pub fn get_mixxx_directory() -> Result<PathBuf, CustomUserError> {
-    use crate::error::MixxxkitExit;
-    use log::error;
-
-    let Some(localappdata) = std::env::var_os("LOCALAPPDATA") else {
-        error!(r#"Could not find Mixxx database because "%localappdata%" is not set"#);
-        return Err(Box::new(MixxxkitExit::Abort));
-    };
-    Ok([localappdata, "Mixxx".into()].iter().collect())
-}
-
-#[cfg(target_os = "macos")]
-#[allow(clippy::unnecessary_wraps)]
-pub fn get_mixxx_directory() -> Result<PathBuf, CustomUserError> {
-    Ok(PathBuf::from(
-        "~/Library/Containers/org.mixxx.mixxx/Data/Library/Application Support/Mixxx",
-    ))
-}
-
-#[cfg(target_os = "linux")]
-#[allow(clippy::unnecessary_wraps)]
-pub fn get_mixxx_directory() -> Result<PathBuf, CustomUserError> {
-    Ok(PathBuf::from("~/.mixxx/"))
+/// Run `PRAGMA integrity_check`, returning the problems it reports if any
+pub async fn check_integrity<C: ConnectionTrait>(db: &C) -> Result<Option<String>, DbErr> {
+    let rows = db
+        .query_all(Statement::from_string(
+            DatabaseBackend::Sqlite,
+            "PRAGMA integrity_check",
+        ))
+        .await?;
+    let messages = rows
+        .iter()
+        .map(|row| row.try_get_by_index::<String>(0))
+        .collect::<Result<Vec<_>, _>>()?;
+    match messages.as_slice() {
+        [message] if message == "ok" => Ok(None),
+        _ => Ok(Some(messages.join(", "))),
+    }
 }
 
 /// <https://github.com/mixxxdj/mixxx/issues/12328>
@@ -2,7 +2,7 @@
 
 use sea_orm::entity::prelude::*;
 
-#[derive(Clone, Debug, PartialEq, DeriveEntityModel)]
+#[derive(Clone, Debug, Default, PartialEq, DeriveEntityModel)]
 #[sea_orm(table_name = "library")]
 pub struct Model {
     #[sea_orm(primary_key)]
@@ -63,7 +63,7 @@ pub struct Model {
     #[sea_orm(column_type = "Binary(BlobSize::Blob(None))", nullable)]
     pub coverart_digest: Option<Vec<u8>>,
     pub last_played_at: Option<DateTime>,
-    pub source_synchronized_ms: Option<i32>,
+    pub source_synchronized_ms: Option<i64>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,219 @@
+use crate::error::MixxxkitExit;
+use inquire::CustomUserError;
+use log::{debug, error, info, warn};
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub const DATABASE_FILE: &str = "mixxxdb.sqlite";
+
+static OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
+/// A place where Mixxx may keep its settings directory
+#[derive(Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub label: &'static str,
+    pub path: PathBuf,
+}
+
+/// Use this settings directory instead of searching for one
+pub fn set_override(path: &Path) {
+    let path = match std::env::var_os("HOME") {
+        Some(home) => expand_home(path, Path::new(&home)),
+        None => path.to_path_buf(),
+    };
+    OVERRIDE.get_or_init(|| path);
+}
+
+pub fn get_mixxx_database_path() -> Result<PathBuf, CustomUserError> {
+    Ok(get_mixxx_directory()?.join(DATABASE_FILE))
+}
+
+pub fn get_mixxx_directory() -> Result<PathBuf, CustomUserError> {
+    if let Some(path) = OVERRIDE.get() {
+        info!(r#"Using Mixxx settings directory "{}""#, path.display());
+        return Ok(path.clone());
+    }
+    let candidates = get_candidates(|key| std::env::var_os(key))?;
+    for candidate in &candidates {
+        debug!(
+            r#"Checking {} settings directory "{}""#,
+            candidate.label,
+            candidate.path.display()
+        );
+    }
+    match choose(&candidates, |path| path.join(DATABASE_FILE).is_file()) {
+        Some(found) => {
+            info!(
+                r#"Using {} Mixxx settings directory "{}""#,
+                found.label,
+                found.path.display()
+            );
+            Ok(found.path.clone())
+        }
+        None => {
+            let Some(fallback) = candidates.into_iter().next() else {
+                error!("Could not find any location to look for the Mixxx settings directory");
+                return Err(Box::new(MixxxkitExit::Abort));
+            };
+            warn!(
+                r#"Could not find a Mixxx database, falling back to {} settings directory "{}". Pass --settings-path to choose another."#,
+                fallback.label,
+                fallback.path.display()
+            );
+            Ok(fallback.path)
+        }
+    }
+}
+
+/// Pick the first candidate that contains a database
+fn choose(candidates: &[Candidate], has_database: impl Fn(&Path) -> bool) -> Option<&Candidate> {
+    candidates
+        .iter()
+        .find(|candidate| has_database(&candidate.path))
+}
+
+/// Replace a leading `~` with the home directory
+fn expand_home(path: &Path, home: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn get_home(var: &impl Fn(&str) -> Option<OsString>) -> Result<PathBuf, CustomUserError> {
+    match var("HOME") {
+        Some(home) if !home.is_empty() => Ok(PathBuf::from(home)),
+        _ => {
+            error!(r#"Could not find Mixxx database because "$HOME" is not set"#);
+            Err(Box::new(MixxxkitExit::Abort))
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn get_candidates(
+    var: impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<Candidate>, CustomUserError> {
+    let Some(localappdata) = var("LOCALAPPDATA") else {
+        error!(r#"Could not find Mixxx database because "%localappdata%" is not set"#);
+        return Err(Box::new(MixxxkitExit::Abort));
+    };
+    Ok(vec![Candidate {
+        label: "local app data",
+        path: PathBuf::from(localappdata).join("Mixxx"),
+    }])
+}
+
+#[cfg(target_os = "macos")]
+fn get_candidates(
+    var: impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<Candidate>, CustomUserError> {
+    let home = get_home(&var)?;
+    Ok(vec![
+        Candidate {
+            label: "sandboxed",
+            path: home
+                .join("Library/Containers/org.mixxx.mixxx/Data/Library/Application Support/Mixxx"),
+        },
+        Candidate {
+            label: "application support",
+            path: home.join("Library/Application Support/Mixxx"),
+        },
+    ])
+}
+
+/// Classic location first, then XDG base directories, then the Flatpak sandbox
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn get_candidates(
+    var: impl Fn(&str) -> Option<OsString>,
+) -> Result<Vec<Candidate>, CustomUserError> {
+    let home = get_home(&var)?;
+    let xdg = |key: &str, default: &str| match var(key) {
+        Some(dir) if Path::new(&dir).is_absolute() => PathBuf::from(dir),
+        _ => home.join(default),
+    };
+    Ok(vec![
+        Candidate {
+            label: "classic",
+            path: home.join(".mixxx"),
+        },
+        Candidate {
+            label: "XDG data",
+            path: xdg("XDG_DATA_HOME", ".local/share").join("mixxx"),
+        },
+        Candidate {
+            label: "XDG config",
+            path: xdg("XDG_CONFIG_HOME", ".config").join("mixxx"),
+        },
+        Candidate {
+            label: "Flatpak",
+            path: home.join(".var/app/org.mixxx.Mixxx/.mixxx"),
+        },
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(label: &'static str, path: &str) -> Candidate {
+        Candidate {
+            label,
+            path: PathBuf::from(path),
+        }
+    }
+
+    #[test]
+    fn chooses_first_with_database() {
+        let candidates = [
+            candidate("classic", "/home/dj/.mixxx"),
+            candidate("XDG data", "/home/dj/.local/share/mixxx"),
+            candidate("Flatpak", "/home/dj/.var/app/org.mixxx.Mixxx/.mixxx"),
+        ];
+        let found = choose(&candidates, |path| path.ends_with("mixxx"));
+        assert_eq!(found, Some(&candidates[1]));
+        assert_eq!(choose(&candidates, |_| false), None);
+    }
+
+    #[test]
+    fn expands_leading_tilde() {
+        let home = Path::new("/home/dj");
+        assert_eq!(
+            expand_home(Path::new("~/.mixxx"), home),
+            PathBuf::from("/home/dj/.mixxx")
+        );
+        assert_eq!(expand_home(Path::new("~"), home), PathBuf::from("/home/dj"));
+        assert_eq!(
+            expand_home(Path::new("/srv/~/mixxx"), home),
+            PathBuf::from("/srv/~/mixxx")
+        );
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[test]
+    fn lists_linux_candidates() {
+        let var = |key: &str| match key {
+            "HOME" => Some(OsString::from("/home/dj")),
+            "XDG_DATA_HOME" => Some(OsString::from("/data")),
+            "XDG_CONFIG_HOME" => Some(OsString::from("relative")),
+            _ => None,
+        };
+        let paths: Vec<_> = get_candidates(var)
+            .unwrap()
+            .into_iter()
+            .map(|candidate| candidate.path)
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                "/home/dj/.mixxx",
+                "/data/mixxx",
+                "/home/dj/.config/mixxx",
+                "/home/dj/.var/app/org.mixxx.Mixxx/.mixxx",
+            ]
+            .map(PathBuf::from)
+        );
+    }
+}
@@ -4,4 +4,6 @@ use thiserror::Error;
 pub enum MixxxkitExit {
     #[error("Program aborted")]
     Abort,
+    #[error("Mixxx is running")]
+    MixxxRunning,
 }
@@ -0,0 +1,246 @@
+//! `iTunes Music Library.xml` as written by iTunes and, when enabled under
+//! *Advanced > Share Library XML with other applications*, by Apple Music
+
+use super::{DecodeFileUri, Error, Library, Playlist, Result, Track};
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Ratings go from 0 to 100 in steps of 20 per star
+const RATING_PER_STAR: i32 = 20;
+
+pub fn read(path: &Path) -> Result<Library> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse(contents: &str) -> Result<Library> {
+    // Property lists always declare their DTD
+    let options = ParsingOptions {
+        allow_dtd: true,
+        ..ParsingOptions::default()
+    };
+    let doc = Document::parse_with_options(contents.trim_start_matches('\u{feff}'), options)?;
+    let Some(root) = doc
+        .root_element()
+        .children()
+        .find(|node| node.has_tag_name("dict"))
+    else {
+        return Err(Error::Invalid("missing top level dict".into()));
+    };
+    let root = to_map(root);
+
+    let mut library = Library::default();
+    let mut by_id = HashMap::new();
+    if let Some(tracks) = root.get("Tracks") {
+        for node in tracks.children().filter(|node| node.has_tag_name("dict")) {
+            let fields = to_map(node);
+            let Some(track) = parse_track(&fields) else {
+                continue;
+            };
+            if let Some(id) = fields.get("Track ID").and_then(Node::text) {
+                by_id.insert(id, library.tracks.len());
+            }
+            library.tracks.push(track);
+        }
+    }
+
+    let playlists: Vec<_> = root
+        .get("Playlists")
+        .into_iter()
+        .flat_map(Node::children)
+        .filter(|node| node.has_tag_name("dict"))
+        .map(to_map)
+        .collect();
+    let folders: HashMap<&str, &HashMap<&str, Node>> = playlists
+        .iter()
+        .filter_map(|fields| Some((fields.get("Playlist Persistent ID")?.text()?, fields)))
+        .collect();
+    for fields in &playlists {
+        // Skip the whole library, built in lists like *Music* or *Podcasts*
+        // and hidden ones
+        let is_special = fields.contains_key("Distinguished Kind")
+            || ["Master", "Folder"]
+                .iter()
+                .any(|key| fields.get(key).is_some_and(|value| is_true(*value)))
+            || fields.get("Visible").is_some_and(|value| !is_true(*value));
+        if is_special {
+            continue;
+        }
+        let entries = fields
+            .get("Playlist Items")
+            .into_iter()
+            .flat_map(Node::children)
+            .filter(|item| item.has_tag_name("dict"))
+            .filter_map(|item| {
+                let id = to_map(item).get("Track ID")?.text()?;
+                by_id.get(id).copied()
+            })
+            .collect();
+        library.playlists.push(Playlist {
+            path: get_path(fields, &folders),
+            entries,
+        });
+    }
+    Ok(library)
+}
+
+fn parse_track(fields: &HashMap<&str, Node>) -> Option<Track> {
+    let get_integer = |key: &str| {
+        fields
+            .get(key)
+            .and_then(Node::text)
+            .and_then(|value| value.parse::<i32>().ok())
+    };
+    // Ratings computed from the album rating were never set by the user
+    let is_computed = fields
+        .get("Rating Computed")
+        .is_some_and(|value| is_true(*value));
+    Some(Track {
+        location: fields
+            .get("Location")?
+            .text()?
+            .decode_file_uri()
+            .replace('\\', "/"),
+        rating: get_integer("Rating")
+            .filter(|_| !is_computed)
+            .map(|rating| (rating / RATING_PER_STAR).clamp(0, 5)),
+        play_count: get_integer("Play Count"),
+        sample_rate: get_integer("Sample Rate").filter(|rate| *rate > 0),
+        ..Track::default()
+    })
+}
+
+/// Names of the enclosing folders followed by the name of the playlist
+fn get_path(
+    fields: &HashMap<&str, Node>,
+    folders: &HashMap<&str, &HashMap<&str, Node>>,
+) -> Vec<String> {
+    let mut path = Vec::new();
+    let mut current = Some(fields);
+    // Guard against folders that are their own ancestors
+    while let Some(fields) = current.filter(|_| path.len() <= folders.len()) {
+        let name = fields.get("Name").and_then(Node::text);
+        path.push(name.unwrap_or_default().to_owned());
+        current = fields
+            .get("Parent Persistent ID")
+            .and_then(Node::text)
+            .and_then(|parent| folders.get(parent).copied());
+    }
+    path.reverse();
+    path
+}
+
+/// Pair up the `key` elements of a `dict` with the values following them
+fn to_map<'a, 'input>(dict: Node<'a, 'input>) -> HashMap<&'a str, Node<'a, 'input>> {
+    let elements: Vec<_> = dict.children().filter(Node::is_element).collect();
+    elements
+        .chunks_exact(2)
+        .filter(|pair| pair[0].has_tag_name("key"))
+        .filter_map(|pair| Some((pair[0].text()?, pair[1])))
+        .collect()
+}
+
+fn is_true(node: Node) -> bool {
+    node.has_tag_name("true")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const LIBRARY: &str = indoc! {r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <!DOCTYPE plist PUBLIC "-//Apple Computer//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+        <plist version="1.0">
+        <dict>
+          <key>Major Version</key><integer>1</integer>
+          <key>Tracks</key>
+          <dict>
+            <key>101</key>
+            <dict>
+              <key>Track ID</key><integer>101</integer>
+              <key>Name</key><string>Song</string>
+              <key>Play Count</key><integer>12</integer>
+              <key>Rating</key><integer>80</integer>
+              <key>Location</key><string>file://localhost/Users/dj/Music/Caf%C3%A9.mp3</string>
+            </dict>
+            <key>102</key>
+            <dict>
+              <key>Track ID</key><integer>102</integer>
+              <key>Rating</key><integer>60</integer>
+              <key>Rating Computed</key><true/>
+              <key>Location</key><string>file://localhost/C:/Music/b.m4a</string>
+            </dict>
+            <key>103</key>
+            <dict>
+              <key>Track ID</key><integer>103</integer>
+              <key>Name</key><string>Radio</string>
+            </dict>
+          </dict>
+          <key>Playlists</key>
+          <array>
+            <dict>
+              <key>Name</key><string>Library</string>
+              <key>Master</key><true/>
+              <key>Visible</key><false/>
+              <key>Playlist Items</key>
+              <array><dict><key>Track ID</key><integer>101</integer></dict></array>
+            </dict>
+            <dict>
+              <key>Name</key><string>Music</string>
+              <key>Distinguished Kind</key><integer>4</integer>
+            </dict>
+            <dict>
+              <key>Name</key><string>Gigs</string>
+              <key>Playlist Persistent ID</key><string>AAAA</string>
+              <key>Folder</key><true/>
+            </dict>
+            <dict>
+              <key>Name</key><string>Friday</string>
+              <key>Playlist Persistent ID</key><string>BBBB</string>
+              <key>Parent Persistent ID</key><string>AAAA</string>
+              <key>Playlist Items</key>
+              <array>
+                <dict><key>Track ID</key><integer>102</integer></dict>
+                <dict><key>Track ID</key><integer>103</integer></dict>
+                <dict><key>Track ID</key><integer>101</integer></dict>
+              </array>
+            </dict>
+          </array>
+        </dict>
+        </plist>
+    "#};
+
+    #[test]
+    fn parses_tracks() {
+        let library = parse(LIBRARY).unwrap();
+        assert_eq!(
+            library.tracks,
+            [
+                Track {
+                    location: "/Users/dj/Music/Café.mp3".into(),
+                    rating: Some(4),
+                    play_count: Some(12),
+                    ..Track::default()
+                },
+                Track {
+                    location: "C:/Music/b.m4a".into(),
+                    ..Track::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_special_playlists_and_nests_folders() {
+        let library = parse(LIBRARY).unwrap();
+        assert_eq!(
+            library.playlists,
+            [Playlist {
+                path: vec!["Gigs".into(), "Friday".into()],
+                entries: vec![1, 0],
+            }]
+        );
+    }
+}
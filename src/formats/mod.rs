@@ -0,0 +1,202 @@
+//! Readers and writers for the collections of other DJ applications, reduced
+//! to the parts that Mixxx can store
+
+pub mod itunes;
+pub mod rekordbox;
+pub mod rhythmbox;
+pub mod serato;
+pub mod traktor;
+mod uri;
+
+pub use uri::{encode_file_uri, percent_decode, DecodeFileUri};
+
+use std::collections::HashSet;
+
+/// Collection read from or written for another application
+#[derive(Debug, Default, PartialEq)]
+pub struct Library {
+    pub tracks: Vec<Track>,
+    pub playlists: Vec<Playlist>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Track {
+    /// Path to the audio file with forward slashes
+    pub location: String,
+    /// Other paths the file may be at, tried in order when `location` is not in the library
+    pub fallback_locations: Vec<String>,
+    /// Stars from 0 to 5
+    pub rating: Option<i32>,
+    /// Color as `0xRRGGBB`
+    pub color: Option<i32>,
+    pub play_count: Option<i32>,
+    pub sample_rate: Option<i32>,
+    pub cues: Vec<Cue>,
+    /// Tags only written to collections, as Mixxx reads its own from the files
+    pub tags: Tags,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Tags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub genre: Option<String>,
+    /// Key in the notation chosen in Mixxx
+    pub key: Option<String>,
+    /// Length in seconds
+    pub duration: Option<f64>,
+    pub bpm: Option<f64>,
+    /// Seconds from the start of the track to the first beat of its grid
+    pub first_beat: Option<f64>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Cue {
+    pub kind: CueKind,
+    /// Seconds from the start of the track
+    pub start: f64,
+    /// Seconds from the start of the track where a loop ends
+    pub end: Option<f64>,
+    /// Zero-based hot cue number
+    pub hotcue: Option<i32>,
+    pub label: String,
+    /// Color as `0xRRGGBB`
+    pub color: Option<i32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CueKind {
+    /// The cue point that Mixxx jumps to when loading a track
+    Main,
+    HotCue,
+    Loop,
+}
+
+/// Playlist whose entries are indices into [`Library::tracks`]
+#[derive(Debug, Default, PartialEq)]
+pub struct Playlist {
+    /// Names of the enclosing folders followed by the name of the playlist
+    pub path: Vec<String>,
+    pub entries: Vec<usize>,
+}
+
+/// Child of a folder in a tree of playlists
+pub enum TreeNode<'a> {
+    /// Playlist along with its name, which gets a numbered suffix when another
+    /// playlist in the same folder already has it
+    Playlist(String, &'a Playlist),
+    Folder(&'a str, Vec<&'a Playlist>),
+}
+
+/// Group playlists by the folder their paths name at `depth`, keeping each
+/// playlist that ends there as its own node, in the order they first appear
+pub fn get_children<'a>(playlists: &[&'a Playlist], depth: usize) -> Vec<TreeNode<'a>> {
+    let mut children: Vec<TreeNode> = Vec::new();
+    let mut names = HashSet::new();
+    for playlist in playlists {
+        let Some(name) = playlist.path.get(depth) else {
+            continue;
+        };
+        if playlist.path.len() == depth + 1 {
+            let mut unused = name.clone();
+            let mut suffix = 2;
+            while !names.insert(unused.clone()) {
+                unused = format!("{name} ({suffix})");
+                suffix += 1;
+            }
+            children.push(TreeNode::Playlist(unused, playlist));
+            continue;
+        }
+        let folder = children.iter_mut().find_map(|child| match child {
+            TreeNode::Folder(folder, members) if folder == name => Some(members),
+            _ => None,
+        });
+        match folder {
+            Some(members) => members.push(playlist),
+            None => children.push(TreeNode::Folder(name, vec![playlist])),
+        }
+    }
+    children
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    #[error("Could not read collection {0:?}")]
+    Io(#[from] std::io::Error),
+    #[error("Unable to parse collection: {0}")]
+    Xml(#[from] roxmltree::Error),
+    #[error("Unable to parse collection: {0}")]
+    Invalid(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Escape text for use in XML attributes
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Convert a rating from 0 to 255 as used by Rekordbox and Traktor into stars
+pub fn get_stars(rating: i32) -> i32 {
+    ((rating + 25) / 51).clamp(0, 5)
+}
+
+/// Convert stars into a rating from 0 to 255, the inverse of [`get_stars`]
+pub fn from_stars(stars: i32) -> i32 {
+    stars.clamp(0, 5) * 51
+}
+
+/// Hot cue numbers may have gaps, so cues that should become hot cues without
+/// having a number of their own are placed in the first unused slots
+pub fn get_free_hotcues(cues: &[Cue]) -> impl Iterator<Item = i32> + '_ {
+    (0..).filter(|slot| !cues.iter().any(|cue| cue.hotcue == Some(*slot)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn playlist(path: &[&str], entries: Vec<usize>) -> Playlist {
+        Playlist {
+            path: path.iter().map(ToString::to_string).collect(),
+            entries,
+        }
+    }
+
+    #[test]
+    fn keeps_playlists_sharing_a_path() {
+        let playlists = [
+            playlist(&["Gigs"], vec![0]),
+            playlist(&["Gigs", "Friday"], vec![1]),
+            playlist(&["Gigs"], vec![2]),
+        ];
+        let refs: Vec<_> = playlists.iter().collect();
+        let children: Vec<_> = get_children(&refs, 0)
+            .into_iter()
+            .map(|child| match child {
+                TreeNode::Playlist(name, playlist) => (name, playlist.entries.clone()),
+                TreeNode::Folder(name, members) => (format!("{name}/"), vec![members.len()]),
+            })
+            .collect();
+        assert_eq!(
+            children,
+            [
+                ("Gigs".to_owned(), vec![0]),
+                ("Gigs/".to_owned(), vec![1]),
+                ("Gigs (2)".to_owned(), vec![2]),
+            ]
+        );
+    }
+}
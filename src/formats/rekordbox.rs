@@ -0,0 +1,415 @@
+//! Rekordbox XML collections as written by *File > Export Collection in xml format*
+//! and read through the *rekordbox xml* view
+
+use super::{
+    encode_file_uri, escape_xml, from_stars, get_children, get_free_hotcues, get_stars, Cue,
+    CueKind, DecodeFileUri, Error, Library, Playlist, Result, Track, TreeNode,
+};
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// `POSITION_MARK` type of a loop, all others are treated as cues
+const MARK_LOOP: &str = "4";
+const MARK_CUE: &str = "0";
+/// Number of hot cues that CDJs and Rekordbox offer
+const HOTCUE_SLOTS: i32 = 8;
+/// `NODE` type of a playlist, all others are folders
+const NODE_PLAYLIST: &str = "1";
+/// `KeyType` of playlists that refer to tracks by location instead of `TrackID`
+const KEY_LOCATION: &str = "1";
+
+pub fn read(path: &Path) -> Result<Library> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse(contents: &str) -> Result<Library> {
+    let doc = Document::parse(contents.trim_start_matches('\u{feff}'))?;
+    let root = doc.root_element();
+    let Some(collection) = root.children().find(|node| node.has_tag_name("COLLECTION")) else {
+        return Err(Error::Invalid("missing COLLECTION".into()));
+    };
+
+    let mut library = Library::default();
+    let mut by_id = HashMap::new();
+    let mut by_location = HashMap::new();
+    for node in collection
+        .children()
+        .filter(|node| node.has_tag_name("TRACK"))
+    {
+        let Some(track) = parse_track(node) else {
+            continue;
+        };
+        let index = library.tracks.len();
+        if let Some(id) = node.attribute("TrackID") {
+            by_id.insert(id.to_owned(), index);
+        }
+        if let Some(location) = node.attribute("Location") {
+            by_location.insert(location.to_owned(), index);
+        }
+        library.tracks.push(track);
+    }
+
+    if let Some(tree) = root.children().find(|node| node.has_tag_name("PLAYLISTS")) {
+        for top in tree.children().filter(|node| node.has_tag_name("NODE")) {
+            // The single top level node is the unnamed root folder
+            for node in top.children().filter(|node| node.has_tag_name("NODE")) {
+                collect_playlists(node, &mut Vec::new(), &by_id, &by_location, &mut library);
+            }
+        }
+    }
+    Ok(library)
+}
+
+pub fn write(path: &Path, library: &Library) -> Result<()> {
+    std::fs::write(path, format(library))?;
+    Ok(())
+}
+
+pub fn format(library: &Library) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<DJ_PLAYLISTS Version=\"1.0.0\">\n");
+    let _ = writeln!(
+        xml,
+        r#"  <PRODUCT Name="mixxxkit" Version="{}" Company="mixxxkit"/>"#,
+        env!("CARGO_PKG_VERSION")
+    );
+    let _ = writeln!(xml, r#"  <COLLECTION Entries="{}">"#, library.tracks.len());
+    for (id, track) in (1..).zip(&library.tracks) {
+        format_track(&mut xml, id, track);
+    }
+    xml.push_str("  </COLLECTION>\n  <PLAYLISTS>\n");
+    let playlists: Vec<&Playlist> = library.playlists.iter().collect();
+    format_node(&mut xml, "ROOT", &playlists, 0, 2);
+    xml.push_str("  </PLAYLISTS>\n</DJ_PLAYLISTS>\n");
+    xml
+}
+
+fn format_track(xml: &mut String, id: usize, track: &Track) {
+    let tags = &track.tags;
+    let _ = write!(
+        xml,
+        r#"    <TRACK TrackID="{id}" Location="{}""#,
+        escape_xml(&encode_file_uri(&track.location))
+    );
+    let texts = [
+        ("Name", &tags.title),
+        ("Artist", &tags.artist),
+        ("Album", &tags.album),
+        ("Genre", &tags.genre),
+        ("Tonality", &tags.key),
+    ];
+    for (name, value) in texts {
+        if let Some(value) = value {
+            let _ = write!(xml, r#" {name}="{}""#, escape_xml(value));
+        }
+    }
+    if let Some(duration) = tags.duration {
+        let _ = write!(xml, r#" TotalTime="{duration:.0}""#);
+    }
+    if let Some(bpm) = tags.bpm {
+        let _ = write!(xml, r#" AverageBpm="{bpm:.2}""#);
+    }
+    if let Some(rate) = track.sample_rate {
+        let _ = write!(xml, r#" SampleRate="{rate}""#);
+    }
+    if let Some(count) = track.play_count {
+        let _ = write!(xml, r#" PlayCount="{count}""#);
+    }
+    if let Some(rating) = track.rating {
+        let _ = write!(xml, r#" Rating="{}""#, from_stars(rating));
+    }
+    if let Some(color) = track.color {
+        let _ = write!(xml, r#" Colour="0x{color:06X}""#);
+    }
+    xml.push_str(">\n");
+
+    if let (Some(bpm), Some(first_beat)) = (tags.bpm, tags.first_beat) {
+        let _ = writeln!(
+            xml,
+            r#"      <TEMPO Inizio="{first_beat:.3}" Bpm="{bpm:.2}" Metro="4/4" Battito="1"/>"#
+        );
+    }
+    for cue in &track.cues {
+        format_mark(xml, cue);
+    }
+    xml.push_str("    </TRACK>\n");
+}
+
+/// Main cues become memory cues, as do hot cues beyond the slots of a CDJ
+fn format_mark(xml: &mut String, cue: &Cue) {
+    let (kind, num) = match cue.kind {
+        CueKind::Main => (MARK_CUE, -1),
+        CueKind::HotCue => (MARK_CUE, cue.hotcue.unwrap_or(-1)),
+        CueKind::Loop => (MARK_LOOP, cue.hotcue.unwrap_or(-1)),
+    };
+    let num = if num < HOTCUE_SLOTS { num } else { -1 };
+    let _ = write!(
+        xml,
+        r#"      <POSITION_MARK Name="{}" Type="{kind}" Start="{:.3}""#,
+        escape_xml(&cue.label),
+        cue.start
+    );
+    if let Some(end) = cue.end.filter(|_| cue.kind == CueKind::Loop) {
+        let _ = write!(xml, r#" End="{end:.3}""#);
+    }
+    let _ = write!(xml, r#" Num="{num}""#);
+    if let Some(color) = cue.color.filter(|_| num >= 0) {
+        let _ = write!(
+            xml,
+            r#" Red="{}" Green="{}" Blue="{}""#,
+            (color >> 16) & 0xFF,
+            (color >> 8) & 0xFF,
+            color & 0xFF
+        );
+    }
+    xml.push_str("/>\n");
+}
+
+/// Write a folder holding the playlists whose paths continue below `depth`
+fn format_node(xml: &mut String, name: &str, playlists: &[&Playlist], depth: usize, indent: usize) {
+    let children = get_children(playlists, depth);
+    let pad = " ".repeat(indent * 2);
+    let _ = writeln!(
+        xml,
+        r#"{pad}<NODE Type="0" Name="{}" Count="{}">"#,
+        escape_xml(name),
+        children.len()
+    );
+    for child in children {
+        match child {
+            TreeNode::Playlist(playlist_name, playlist) => {
+                let _ = writeln!(
+                    xml,
+                    r#"{pad}  <NODE Name="{}" Type="{NODE_PLAYLIST}" KeyType="0" Entries="{}">"#,
+                    escape_xml(&playlist_name),
+                    playlist.entries.len()
+                );
+                for entry in &playlist.entries {
+                    let _ = writeln!(xml, r#"{pad}    <TRACK Key="{}"/>"#, entry + 1);
+                }
+                let _ = writeln!(xml, "{pad}  </NODE>");
+            }
+            TreeNode::Folder(folder, members) => {
+                format_node(xml, folder, &members, depth + 1, indent + 1);
+            }
+        }
+    }
+    let _ = writeln!(xml, "{pad}</NODE>");
+}
+
+fn parse_track(node: Node) -> Option<Track> {
+    let location = node
+        .attribute("Location")?
+        .decode_file_uri()
+        .replace('\\', "/");
+    let mut cues: Vec<Cue> = node
+        .children()
+        .filter(|mark| mark.has_tag_name("POSITION_MARK"))
+        .filter_map(parse_mark)
+        .collect();
+    place_memory_cues(&mut cues);
+    Some(Track {
+        location,
+        rating: node
+            .attribute("Rating")
+            .and_then(|rating| rating.parse::<i32>().ok())
+            .map(get_stars),
+        color: node.attribute("Colour").and_then(parse_color),
+        sample_rate: node
+            .attribute("SampleRate")
+            .and_then(|rate| rate.parse::<i32>().ok())
+            .filter(|rate| *rate > 0),
+        cues,
+        ..Track::default()
+    })
+}
+
+/// Hot cues have a `Num` of zero or above while memory cues use `-1`
+fn parse_mark(node: Node) -> Option<Cue> {
+    let start = node.attribute("Start")?.parse::<f64>().ok()?;
+    let end = node
+        .attribute("End")
+        .and_then(|end| end.parse::<f64>().ok());
+    let hotcue = node
+        .attribute("Num")
+        .and_then(|num| num.parse::<i32>().ok())
+        .filter(|num| *num >= 0);
+    let color = match (
+        node.attribute("Red"),
+        node.attribute("Green"),
+        node.attribute("Blue"),
+    ) {
+        (Some(red), Some(green), Some(blue)) => {
+            let channel = |value: &str| value.parse::<u8>().ok().map(i32::from);
+            Some((channel(red)? << 16) | (channel(green)? << 8) | channel(blue)?)
+        }
+        _ => None,
+    };
+    Some(Cue {
+        kind: match (node.attribute("Type"), end) {
+            (Some(MARK_LOOP), Some(_)) => CueKind::Loop,
+            _ => CueKind::HotCue,
+        },
+        start,
+        end,
+        hotcue,
+        label: node.attribute("Name").unwrap_or_default().to_owned(),
+        color,
+    })
+}
+
+/// Mixxx has no memory cues, so the first memory cue becomes the main cue and
+/// every memory cue is kept as a hot cue in an unused slot
+fn place_memory_cues(cues: &mut Vec<Cue>) {
+    let memory: Vec<usize> = (0..cues.len())
+        .filter(|i| cues[*i].hotcue.is_none())
+        .collect();
+    let slots: Vec<i32> = get_free_hotcues(cues).take(memory.len()).collect();
+    if let Some(first) = memory.first().map(|i| &cues[*i]) {
+        cues.push(Cue {
+            kind: CueKind::Main,
+            start: first.start,
+            end: None,
+            hotcue: None,
+            label: String::new(),
+            color: None,
+        });
+    }
+    for (i, slot) in memory.into_iter().zip(slots) {
+        cues[i].hotcue = Some(slot);
+    }
+}
+
+fn parse_color(color: &str) -> Option<i32> {
+    let hex = color.strip_prefix("0x").unwrap_or(color);
+    i32::from_str_radix(hex, 16).ok()
+}
+
+fn collect_playlists(
+    node: Node,
+    path: &mut Vec<String>,
+    by_id: &HashMap<String, usize>,
+    by_location: &HashMap<String, usize>,
+    library: &mut Library,
+) {
+    path.push(node.attribute("Name").unwrap_or_default().to_owned());
+    if node.attribute("Type") == Some(NODE_PLAYLIST) {
+        let lookup = match node.attribute("KeyType") {
+            Some(KEY_LOCATION) => by_location,
+            _ => by_id,
+        };
+        let entries = node
+            .children()
+            .filter(|entry| entry.has_tag_name("TRACK"))
+            .filter_map(|entry| lookup.get(entry.attribute("Key")?).copied())
+            .collect();
+        library.playlists.push(Playlist {
+            path: path.clone(),
+            entries,
+        });
+    } else {
+        for child in node.children().filter(|child| child.has_tag_name("NODE")) {
+            collect_playlists(child, path, by_id, by_location, library);
+        }
+    }
+    path.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::Tags;
+    use indoc::indoc;
+
+    const COLLECTION: &str = indoc! {r#"
+        <?xml version="1.0" encoding="UTF-8"?>
+        <DJ_PLAYLISTS Version="1.0.0">
+          <PRODUCT Name="rekordbox" Version="6.8.2" Company="AlphaTheta"/>
+          <COLLECTION Entries="2">
+            <TRACK TrackID="11" Name="Song" Artist="Artist" SampleRate="44100" Rating="204"
+                   Colour="0xFF007F" Location="file://localhost/C:/Music/Caf%C3%A9.mp3">
+              <TEMPO Inizio="0.025" Bpm="128.00" Metro="4/4" Battito="1"/>
+              <POSITION_MARK Name="" Type="0" Start="1.5" Num="-1"/>
+              <POSITION_MARK Name="Drop" Type="0" Start="32.0" Num="0" Red="40" Green="226" Blue="20"/>
+              <POSITION_MARK Name="Roll" Type="4" Start="64.0" End="66.0" Num="2"/>
+            </TRACK>
+            <TRACK TrackID="12" Location="file://localhost/Users/dj/Music/b.flac"/>
+          </COLLECTION>
+          <PLAYLISTS>
+            <NODE Type="0" Name="ROOT" Count="2">
+              <NODE Type="0" Name="Gigs" Count="1">
+                <NODE Name="Friday" Type="1" KeyType="0" Entries="3">
+                  <TRACK Key="12"/>
+                  <TRACK Key="11"/>
+                  <TRACK Key="99"/>
+                </NODE>
+              </NODE>
+              <NODE Name="By Path" Type="1" KeyType="1" Entries="1">
+                <TRACK Key="file://localhost/Users/dj/Music/b.flac"/>
+              </NODE>
+            </NODE>
+          </PLAYLISTS>
+        </DJ_PLAYLISTS>
+    "#};
+
+    #[test]
+    fn parses_tracks_and_cues() {
+        let library = parse(COLLECTION).unwrap();
+        let track = &library.tracks[0];
+        assert_eq!(track.location, "C:/Music/Café.mp3");
+        assert_eq!(track.rating, Some(4));
+        assert_eq!(track.color, Some(0x00FF_007F));
+        assert_eq!(track.sample_rate, Some(44100));
+        assert_eq!(library.tracks[1].location, "/Users/dj/Music/b.flac");
+
+        let hotcues: Vec<_> = track
+            .cues
+            .iter()
+            .map(|cue| (cue.kind, cue.hotcue, cue.start))
+            .collect();
+        assert_eq!(
+            hotcues,
+            [
+                (CueKind::HotCue, Some(1), 1.5),
+                (CueKind::HotCue, Some(0), 32.0),
+                (CueKind::Loop, Some(2), 64.0),
+                (CueKind::Main, None, 1.5),
+            ]
+        );
+        assert_eq!(track.cues[1].color, Some(0x0028_E214));
+        assert_eq!(track.cues[2].end, Some(66.0));
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let mut library = parse(COLLECTION).unwrap();
+        // The main cue would come back as another memory cue
+        library.tracks[0].cues.pop();
+        library.playlists.pop();
+        library.tracks[0].tags.title = Some("Song & Dance".into());
+        let xml = format(&library);
+        assert!(xml.contains(r#"Name="Song &amp; Dance""#));
+        library.tracks[0].tags = Tags::default();
+        assert_eq!(parse(&xml).unwrap(), library);
+    }
+
+    #[test]
+    fn flattens_playlist_tree() {
+        let library = parse(COLLECTION).unwrap();
+        assert_eq!(
+            library.playlists,
+            [
+                Playlist {
+                    path: vec!["Gigs".into(), "Friday".into()],
+                    entries: vec![1, 0],
+                },
+                Playlist {
+                    path: vec!["By Path".into()],
+                    entries: vec![1],
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,354 @@
+//! Rhythmbox's `rhythmdb.xml` along with the `playlists.xml` next to it,
+//! usually found in `~/.local/share/rhythmbox`
+
+use super::{DecodeFileUri, Error, Library, Playlist, Result, Track};
+use log::warn;
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DATABASE_FILE: &str = "rhythmdb.xml";
+const PLAYLISTS_FILE: &str = "playlists.xml";
+/// Entries of any other type are radio stations, podcasts or ignored files
+const ENTRY_SONG: &str = "song";
+/// Properties that `search-match` looks through
+const SEARCH_PROPERTIES: [&str; 4] = ["title", "artist", "album", "genre"];
+
+/// Read `rhythmdb.xml`, or the folder containing it
+pub fn read(path: &Path) -> Result<Library> {
+    let database = match path.is_dir() {
+        true => path.join(DATABASE_FILE),
+        false => path.to_path_buf(),
+    };
+    let playlists = database.with_file_name(PLAYLISTS_FILE);
+    let playlists = match playlists.exists() {
+        true => Some(read_to_string(playlists)?),
+        false => None,
+    };
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |elapsed| elapsed.as_secs_f64());
+    parse(&read_to_string(database)?, playlists.as_deref(), now)
+}
+
+/// Parse both files, evaluating automatic playlists at `now` in seconds since
+/// the Unix epoch
+pub fn parse(database: &str, playlists: Option<&str>, now: f64) -> Result<Library> {
+    let doc = Document::parse(database.trim_start_matches('\u{feff}'))?;
+    let root = doc.root_element();
+    if !root.has_tag_name("rhythmdb") {
+        return Err(Error::Invalid("missing rhythmdb".into()));
+    }
+
+    let mut library = Library::default();
+    let mut entries = Vec::new();
+    let mut by_location = HashMap::new();
+    for node in root
+        .children()
+        .filter(|node| node.has_tag_name("entry") && node.attribute("type") == Some(ENTRY_SONG))
+    {
+        let mut properties: HashMap<&str, &str> = node
+            .children()
+            .filter(Node::is_element)
+            .map(|child| (child.tag_name().name(), child.text().unwrap_or_default()))
+            .collect();
+        properties.insert("type", ENTRY_SONG);
+        let Some(location) = properties.get("location").copied() else {
+            continue;
+        };
+        by_location.insert(location, library.tracks.len());
+        library.tracks.push(Track {
+            location: location.decode_file_uri(),
+            rating: Some(to_stars(get_number(&properties, "rating"))).filter(|stars| *stars > 0),
+            play_count: properties
+                .get("play-count")
+                .and_then(|count| count.parse::<i32>().ok()),
+            ..Track::default()
+        });
+        entries.push(properties);
+    }
+
+    let Some(playlists) = playlists else {
+        return Ok(library);
+    };
+    let doc = Document::parse(playlists.trim_start_matches('\u{feff}'))?;
+    for node in doc
+        .root_element()
+        .children()
+        .filter(|node| node.has_tag_name("playlist"))
+    {
+        let name = node.attribute("name").unwrap_or_default();
+        let playlist_entries = match node.attribute("type") {
+            Some("static") => node
+                .children()
+                .filter(|child| child.has_tag_name("location"))
+                .filter_map(|child| by_location.get(child.text()?).copied())
+                .collect(),
+            Some("automatic") => {
+                let Some(query) = parse_automatic(node) else {
+                    warn!(
+                        r#"Automatic playlist "{name}" uses criteria that cannot be converted! Skipping..."#
+                    );
+                    continue;
+                };
+                (0..entries.len())
+                    .filter(|i| query.matches(&entries[*i], now))
+                    .collect()
+            }
+            // The play queue only lives until Rhythmbox is closed
+            _ => continue,
+        };
+        library.playlists.push(Playlist {
+            path: vec![name.to_owned()],
+            entries: playlist_entries,
+        });
+    }
+    Ok(library)
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn to_stars(rating: f64) -> i32 {
+    rating.round().clamp(0.0, 5.0) as i32
+}
+
+/// Missing numeric properties such as `play-count` are zero
+fn get_number(properties: &HashMap<&str, &str>, property: &str) -> f64 {
+    properties
+        .get(property)
+        .and_then(|value| value.parse::<f64>().ok())
+        .unwrap_or_default()
+}
+
+/// Criteria of an automatic playlist, or `None` if the playlist is limited in
+/// size or uses criteria that are not understood
+fn parse_automatic<'a>(node: Node<'a, '_>) -> Option<Conjunction<'a>> {
+    let is_limited = ["limit-count", "limit-size", "limit-time"]
+        .iter()
+        .any(|limit| node.attribute(*limit).is_some_and(|value| value != "0"));
+    if is_limited {
+        return None;
+    }
+    let conjunction = node
+        .children()
+        .find(|child| child.has_tag_name("conjunction"))?;
+    parse_conjunction(conjunction)
+}
+
+/// Groups of criteria, any of which matches when all of its criteria match
+struct Conjunction<'a>(Vec<Vec<Criterion<'a>>>);
+
+enum Criterion<'a> {
+    Subquery(Conjunction<'a>),
+    Compare(Operator, &'a str, &'a str),
+}
+
+#[derive(Clone, Copy)]
+enum Operator {
+    Equals,
+    Like,
+    NotLike,
+    Prefix,
+    Suffix,
+    /// Rhythmbox presents this as *at least*
+    Greater,
+    /// Rhythmbox presents this as *at most*
+    Less,
+    Within,
+    NotWithin,
+}
+
+fn parse_conjunction<'a>(node: Node<'a, '_>) -> Option<Conjunction<'a>> {
+    let mut groups = vec![Vec::new()];
+    for child in node.children().filter(Node::is_element) {
+        let operator = match child.tag_name().name() {
+            "disjunction" => {
+                groups.push(Vec::new());
+                continue;
+            }
+            "subquery" => {
+                let inner = child
+                    .children()
+                    .find(|inner| inner.has_tag_name("conjunction"))?;
+                groups
+                    .last_mut()?
+                    .push(Criterion::Subquery(parse_conjunction(inner)?));
+                continue;
+            }
+            "equals" => Operator::Equals,
+            "like" => Operator::Like,
+            "not-like" => Operator::NotLike,
+            "prefix" => Operator::Prefix,
+            "suffix" => Operator::Suffix,
+            "greater" => Operator::Greater,
+            "less" => Operator::Less,
+            "current-time-within" => Operator::Within,
+            "current-time-not-within" => Operator::NotWithin,
+            _ => return None,
+        };
+        // Text searches compare against case folded variants of properties
+        let property = child.attribute("prop")?;
+        let property = property.strip_suffix("-folded").unwrap_or(property);
+        groups.last_mut()?.push(Criterion::Compare(
+            operator,
+            property,
+            child.text().unwrap_or_default(),
+        ));
+    }
+    Some(Conjunction(groups))
+}
+
+impl Conjunction<'_> {
+    fn matches(&self, properties: &HashMap<&str, &str>, now: f64) -> bool {
+        self.0.iter().any(|group| {
+            group
+                .iter()
+                .all(|criterion| criterion.matches(properties, now))
+        })
+    }
+}
+
+impl Criterion<'_> {
+    fn matches(&self, properties: &HashMap<&str, &str>, now: f64) -> bool {
+        let (operator, property, value) = match self {
+            Criterion::Subquery(conjunction) => return conjunction.matches(properties, now),
+            Criterion::Compare(operator, property, value) => (*operator, *property, *value),
+        };
+        if property == "search-match" {
+            let words: Vec<String> = value.split_whitespace().map(str::to_lowercase).collect();
+            return words.iter().all(|word| {
+                SEARCH_PROPERTIES.iter().any(|property| {
+                    properties
+                        .get(property)
+                        .is_some_and(|text| text.to_lowercase().contains(word))
+                })
+            });
+        }
+
+        let text = properties.get(property).copied().unwrap_or_default();
+        let number = get_number(properties, property);
+        let target = value.parse::<f64>().ok();
+        let (text, value) = (text.to_lowercase(), value.to_lowercase());
+        match operator {
+            Operator::Equals => match target {
+                Some(target) if text.is_empty() || text.parse::<f64>().is_ok() => {
+                    (number - target).abs() < f64::EPSILON
+                }
+                _ => text == value,
+            },
+            Operator::Like => text.contains(&value),
+            Operator::NotLike => !text.contains(&value),
+            Operator::Prefix => text.starts_with(&value),
+            Operator::Suffix => text.ends_with(&value),
+            Operator::Greater => target.is_some_and(|target| number >= target),
+            Operator::Less => target.is_some_and(|target| number <= target),
+            Operator::Within => target.is_some_and(|target| number >= now - target),
+            Operator::NotWithin => target.is_some_and(|target| number < now - target),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const DATABASE: &str = indoc! {r#"
+        <?xml version="1.0" standalone="yes"?>
+        <rhythmdb version="2.0">
+          <entry type="song">
+            <title>Song</title>
+            <artist>Artist</artist>
+            <genre>House</genre>
+            <location>file:///home/dj/Music/Caf%C3%A9.mp3</location>
+            <play-count>7</play-count>
+            <last-played>1000</last-played>
+            <rating>5</rating>
+          </entry>
+          <entry type="iradio">
+            <title>Radio</title>
+            <location>http://radio.example/stream</location>
+          </entry>
+          <entry type="song">
+            <title>Other</title>
+            <genre>Techno</genre>
+            <location>file:///home/dj/Music/b.flac</location>
+          </entry>
+        </rhythmdb>
+    "#};
+
+    const PLAYLISTS: &str = indoc! {r#"
+        <?xml version="1.0"?>
+        <rhythmdb-playlists>
+          <playlist name="Play Queue" show-browser="false" browser-position="180" search-type="search-match" type="queue">
+            <location>file:///home/dj/Music/b.flac</location>
+          </playlist>
+          <playlist name="Friday" show-browser="false" browser-position="180" search-type="search-match" type="static">
+            <location>file:///home/dj/Music/b.flac</location>
+            <location>file:///home/dj/Music/Caf%C3%A9.mp3</location>
+          </playlist>
+          <playlist name="Loved" show-browser="false" browser-position="180" search-type="search-match" type="automatic" sort-key="Rating" sort-direction="1">
+            <conjunction>
+              <subquery>
+                <conjunction>
+                  <equals prop="type">song</equals>
+                </conjunction>
+              </subquery>
+              <greater prop="rating">4</greater>
+            </conjunction>
+          </playlist>
+          <playlist name="Dance" show-browser="false" browser-position="180" search-type="search-match" type="automatic" sort-key="Title" sort-direction="0">
+            <conjunction>
+              <like prop="genre-folded">techno</like>
+              <disjunction/>
+              <current-time-within prop="last-played">500</current-time-within>
+            </conjunction>
+          </playlist>
+          <playlist name="Limited" show-browser="false" browser-position="180" search-type="search-match" type="automatic" limit-count="10">
+            <conjunction>
+              <equals prop="type">song</equals>
+            </conjunction>
+          </playlist>
+        </rhythmdb-playlists>
+    "#};
+
+    #[test]
+    fn parses_songs() {
+        let library = parse(DATABASE, None, 0.0).unwrap();
+        assert_eq!(
+            library.tracks,
+            [
+                Track {
+                    location: "/home/dj/Music/Café.mp3".into(),
+                    rating: Some(5),
+                    play_count: Some(7),
+                    ..Track::default()
+                },
+                Track {
+                    location: "/home/dj/Music/b.flac".into(),
+                    ..Track::default()
+                },
+            ]
+        );
+        assert!(library.playlists.is_empty());
+    }
+
+    #[test]
+    fn evaluates_automatic_playlists() {
+        let library = parse(DATABASE, Some(PLAYLISTS), 1200.0).unwrap();
+        let playlists: Vec<_> = library
+            .playlists
+            .iter()
+            .map(|playlist| (playlist.path[0].as_str(), playlist.entries.clone()))
+            .collect();
+        assert_eq!(
+            playlists,
+            [
+                ("Friday", vec![1, 0]),
+                ("Loved", vec![0]),
+                ("Dance", vec![0, 1]),
+            ]
+        );
+    }
+}
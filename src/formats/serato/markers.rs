@@ -0,0 +1,206 @@
+//! The `Serato Markers2` tag, which holds base64 encoded entries of a name,
+//! a big endian length and a payload each
+
+use crate::formats::{Cue, CueKind};
+
+/// Both the tag and the entries within start with a version of `1.1`
+const VERSION: [u8; 2] = [1, 1];
+/// Track color Serato uses when none is set
+const NO_COLOR: i32 = 0x00FF_FFFF;
+
+#[derive(Debug, Default, PartialEq)]
+pub struct Markers {
+    pub color: Option<i32>,
+    /// Loops are not numbered
+    pub cues: Vec<Cue>,
+}
+
+pub fn parse(data: &[u8]) -> Option<Markers> {
+    let text = data.strip_prefix(&VERSION)?;
+    // The text is padded with null bytes to a minimum size
+    let end = text
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(text.len());
+    let decoded = decode_base64(&text[..end])?;
+    let mut entries = decoded.strip_prefix(&VERSION)?;
+
+    let mut markers = Markers::default();
+    while let Some(end) = entries.iter().position(|byte| *byte == 0) {
+        let name = &entries[..end];
+        let Some(length) = entries
+            .get(end + 1..end + 5)
+            .and_then(|length| usize::try_from(get_u32(length)).ok())
+        else {
+            break;
+        };
+        let Some(payload) = entries.get(end + 5..end + 5 + length) else {
+            break;
+        };
+        match name {
+            b"COLOR" => {
+                markers.color = payload
+                    .get(1..4)
+                    .map(get_rgb)
+                    .filter(|color| *color != NO_COLOR);
+            }
+            b"CUE" => markers.cues.extend(parse_cue(payload)),
+            b"LOOP" => markers.cues.extend(parse_loop(payload)),
+            _ => {}
+        }
+        entries = &entries[end + 5 + length..];
+    }
+    Some(markers)
+}
+
+/// Index, position in milliseconds, color and a null terminated label
+fn parse_cue(payload: &[u8]) -> Option<Cue> {
+    Some(Cue {
+        kind: CueKind::HotCue,
+        start: f64::from(get_u32(payload.get(2..6)?)) / 1000.0,
+        end: None,
+        hotcue: Some(i32::from(*payload.get(1)?)),
+        label: get_label(payload.get(12..)?),
+        color: Some(get_rgb(payload.get(7..10)?)),
+    })
+}
+
+/// Index, start and end in milliseconds, color, lock and a null terminated label
+fn parse_loop(payload: &[u8]) -> Option<Cue> {
+    Some(Cue {
+        kind: CueKind::Loop,
+        start: f64::from(get_u32(payload.get(2..6)?)) / 1000.0,
+        end: Some(f64::from(get_u32(payload.get(6..10)?)) / 1000.0),
+        hotcue: None,
+        label: get_label(payload.get(19..)?),
+        color: Some(get_rgb(payload.get(15..18)?)),
+    })
+}
+
+fn get_u32(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | u32::from(*byte))
+}
+
+fn get_rgb(bytes: &[u8]) -> i32 {
+    bytes
+        .iter()
+        .fold(0, |value, byte| (value << 8) | i32::from(*byte))
+}
+
+fn get_label(bytes: &[u8]) -> String {
+    let end = bytes
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+/// Serato wraps lines and leaves out padding, so both are skipped
+pub fn decode_base64(text: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(text.len() * 3 / 4);
+    let (mut buffer, mut bits) = (0u32, 0);
+    for byte in text {
+        let value = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' | b'\n' | b'\r' => continue,
+            _ => return None,
+        };
+        buffer = (buffer << 6) | u32::from(value);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            decoded.push((buffer >> bits).to_be_bytes()[3]);
+            buffer &= (1 << bits) - 1;
+        }
+    }
+    Some(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encode without padding like Serato does
+    fn encode_base64(data: &[u8]) -> Vec<u8> {
+        const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+        let mut text = Vec::new();
+        for chunk in data.chunks(3) {
+            let buffer = chunk
+                .iter()
+                .chain([0, 0].iter())
+                .take(3)
+                .fold(0u32, |buffer, byte| (buffer << 8) | u32::from(*byte));
+            for i in 0..=chunk.len() {
+                text.push(ALPHABET[((buffer >> (18 - 6 * i)) & 63) as usize]);
+            }
+        }
+        text
+    }
+
+    fn entry(name: &[u8], payload: &[u8]) -> Vec<u8> {
+        let length = u32::try_from(payload.len()).unwrap();
+        [name, &[0], &length.to_be_bytes(), payload].concat()
+    }
+
+    #[test]
+    fn decodes_base64_without_padding() {
+        assert_eq!(decode_base64(b"TWl4\neHg").unwrap(), b"Mixxx");
+        assert_eq!(decode_base64(b"TWl4eHg=").unwrap(), b"Mixxx");
+        assert!(decode_base64(b"T!").is_none());
+    }
+
+    #[test]
+    fn parses_cues_loops_and_color() {
+        let entries = [
+            &VERSION[..],
+            &entry(b"COLOR", &[0, 0xFF, 0x99, 0xFF]),
+            &entry(
+                b"CUE",
+                &[
+                    0, 2, 0, 0, 0x7D, 0, 0, 0xCC, 0, 0, 0, 0, b'D', b'r', b'o', b'p', 0,
+                ],
+            ),
+            &entry(
+                b"LOOP",
+                &[
+                    0, 0, 0, 0, 0x03, 0xE8, 0, 0, 0x07, 0xD0, 0xFF, 0xFF, 0xFF, 0xFF, 0, 0x27,
+                    0xAA, 0xE1, 0, 0,
+                ],
+            ),
+            &entry(b"BPMLOCK", &[0]),
+            &[0],
+        ]
+        .concat();
+        let data = [&VERSION[..], &encode_base64(&entries), &[0; 16]].concat();
+        assert_eq!(
+            parse(&data).unwrap(),
+            Markers {
+                color: Some(0x00FF_99FF),
+                cues: vec![
+                    Cue {
+                        kind: CueKind::HotCue,
+                        start: 32.0,
+                        end: None,
+                        hotcue: Some(2),
+                        label: "Drop".into(),
+                        color: Some(0x00CC_0000),
+                    },
+                    Cue {
+                        kind: CueKind::Loop,
+                        start: 1.0,
+                        end: Some(2.0),
+                        hotcue: None,
+                        label: String::new(),
+                        color: Some(0x0027_AAE1),
+                    },
+                ],
+            }
+        );
+    }
+}
@@ -0,0 +1,204 @@
+//! Serato's `_Serato_` folder, holding the library in `database V2` and crates
+//! in `Subcrates`, along with the `Serato Markers2` tags that Serato writes
+//! into audio files
+
+mod markers;
+mod tags;
+
+use super::{get_free_hotcues, Error, Library, Playlist, Result, Track};
+use log::debug;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SERATO_FOLDER: &str = "_Serato_";
+const DATABASE_FILE: &str = "database V2";
+const CRATES_FOLDER: &str = "Subcrates";
+const CRATE_EXTENSION: &str = "crate";
+/// Serato joins the names of nested crates with this in their file names
+const CRATE_SEPARATOR: &str = "%%";
+
+/// Read a `_Serato_` folder, or the folder containing it
+pub fn read(path: &Path) -> Result<Library> {
+    let folder = match path.join(SERATO_FOLDER).is_dir() {
+        true => path.join(SERATO_FOLDER),
+        false => path.to_path_buf(),
+    };
+    let database = folder.join(DATABASE_FILE);
+    let crates = folder.join(CRATES_FOLDER);
+    if !database.is_file() && !crates.is_dir() {
+        return Err(Error::Invalid(format!(
+            r#"{SERATO_FOLDER} folder not found at "{}""#,
+            path.display()
+        )));
+    }
+    let volume = get_volume(&folder.to_string_lossy().replace('\\', "/"));
+
+    let mut library = Library::default();
+    let mut by_path = HashMap::new();
+    let mut add_track = |library: &mut Library, path: String| {
+        *by_path.entry(path).or_insert_with_key(|path| {
+            library.tracks.push(Track {
+                location: format!("{}/{path}", volume.as_deref().unwrap_or_default()),
+                ..Track::default()
+            });
+            library.tracks.len() - 1
+        })
+    };
+    if database.is_file() {
+        for path in get_paths(&fs::read(database)?, b"pfil")? {
+            add_track(&mut library, path);
+        }
+    }
+    if crates.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(crates)?
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|file| file.extension().is_some_and(|ext| ext == CRATE_EXTENSION))
+            .collect();
+        files.sort();
+        for file in files {
+            let name = file.file_stem().unwrap_or_default().to_string_lossy();
+            let entries = get_paths(&fs::read(&file)?, b"ptrk")?
+                .into_iter()
+                .map(|path| add_track(&mut library, path))
+                .collect();
+            library.playlists.push(Playlist {
+                path: name.split(CRATE_SEPARATOR).map(str::to_owned).collect(),
+                entries,
+            });
+        }
+    }
+
+    for track in &mut library.tracks {
+        load_markers(track);
+    }
+    Ok(library)
+}
+
+/// Serato stores paths relative to the root of the drive holding `_Serato_`
+fn get_volume(folder: &str) -> Option<String> {
+    let mut parts = folder.split('/');
+    let first = parts.next()?;
+    if first.len() == 2 && first.ends_with(':') {
+        return Some(first.to_owned());
+    }
+    let depth = match (parts.next()?, parts.next()?) {
+        ("Volumes", _) => 2,
+        ("media", _) => 3,
+        ("run", "media") => 4,
+        _ => return None,
+    };
+    let volume: Vec<&str> = folder.split('/').take(depth + 1).collect();
+    (volume.len() == depth + 1).then(|| volume.join("/"))
+}
+
+/// Collect the values of `key` within the tracks of a database or crate file,
+/// which consist of a four byte tag, a big endian length and a payload each
+fn get_paths(data: &[u8], key: &[u8]) -> Result<Vec<String>> {
+    let mut paths = Vec::new();
+    for (tag, payload) in get_fields(data)? {
+        if tag != b"otrk" {
+            continue;
+        }
+        for (tag, value) in get_fields(payload)? {
+            if tag == key {
+                paths.push(decode_utf16(value));
+            }
+        }
+    }
+    Ok(paths)
+}
+
+fn get_fields(mut data: &[u8]) -> Result<Vec<(&[u8], &[u8])>> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let field = data.get(4..8).and_then(|length| {
+            let length = usize::try_from(u32::from_be_bytes(length.try_into().ok()?)).ok()?;
+            Some((&data[..4], data.get(8..8 + length)?, 8 + length))
+        });
+        let Some((tag, payload, size)) = field else {
+            return Err(Error::Invalid("truncated Serato field".into()));
+        };
+        fields.push((tag, payload));
+        data = &data[size..];
+    }
+    Ok(fields)
+}
+
+fn decode_utf16(data: &[u8]) -> String {
+    let units: Vec<u16> = data
+        .chunks_exact(2)
+        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Take the color, cues and loops of a track from its audio file
+fn load_markers(track: &mut Track) {
+    let data = match tags::read_markers(Path::new(&track.location)) {
+        Ok(Some(data)) => data,
+        Ok(None) => return,
+        Err(error) => {
+            debug!(r#"Could not read tags of "{}": {error}"#, track.location);
+            return;
+        }
+    };
+    let Some(markers) = markers::parse(&data) else {
+        debug!(r#"Could not parse Serato markers of "{}""#, track.location);
+        return;
+    };
+    track.color = markers.color;
+    track.cues = markers.cues;
+    // Serato numbers loops separately from cues
+    let loops: Vec<usize> = (0..track.cues.len())
+        .filter(|i| track.cues[*i].hotcue.is_none())
+        .collect();
+    let slots: Vec<i32> = get_free_hotcues(&track.cues).take(loops.len()).collect();
+    for (i, slot) in loops.into_iter().zip(slots) {
+        track.cues[i].hotcue = Some(slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field(tag: &[u8], payload: &[u8]) -> Vec<u8> {
+        let length = u32::try_from(payload.len()).unwrap();
+        [tag, &length.to_be_bytes(), payload].concat()
+    }
+
+    fn utf16(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_be_bytes).collect()
+    }
+
+    #[test]
+    fn reads_crate_paths() {
+        let data = [
+            field(b"vrsn", &utf16("1.0/Serato ScratchLive Crate")),
+            field(b"osrt", &field(b"tvcn", &utf16("song"))),
+            field(b"otrk", &field(b"ptrk", &utf16("Users/dj/Music/Café.mp3"))),
+            field(b"otrk", &field(b"ptrk", &utf16("Users/dj/Music/b.flac"))),
+        ]
+        .concat();
+        assert_eq!(
+            get_paths(&data, b"ptrk").unwrap(),
+            ["Users/dj/Music/Café.mp3", "Users/dj/Music/b.flac"]
+        );
+        assert!(get_paths(&data[..data.len() - 1], b"ptrk").is_err());
+    }
+
+    #[test]
+    fn finds_volume_of_serato_folder() {
+        assert_eq!(get_volume("/Users/dj/Music/_Serato_"), None);
+        assert_eq!(
+            get_volume("/Volumes/USB/_Serato_").as_deref(),
+            Some("/Volumes/USB")
+        );
+        assert_eq!(
+            get_volume("/run/media/dj/USB/_Serato_").as_deref(),
+            Some("/run/media/dj/USB")
+        );
+        assert_eq!(get_volume("E:/_Serato_").as_deref(), Some("E:"));
+    }
+}
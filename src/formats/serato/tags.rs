@@ -0,0 +1,187 @@
+//! Finding the raw `Serato Markers2` tag in MP3 and FLAC files
+
+use super::markers::decode_base64;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Description of the ID3 `GEOB` frame holding the markers
+const GEOB_DESCRIPTION: &[u8] = b"Serato Markers2";
+/// Vorbis comment holding the markers as base64, wrapped like a `GEOB` frame
+const VORBIS_COMMENT: &str = "SERATO_MARKERS_V2";
+const FLAC_VORBIS_COMMENT: u8 = 4;
+
+/// Read the markers of an audio file, or `None` if it has none or is of
+/// another format
+pub fn read_markers(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut file = File::open(path)?;
+    let mut header = [0; 10];
+    if file.read_exact(&mut header).is_err() {
+        return Ok(None);
+    }
+    if header.starts_with(b"ID3") {
+        let mut tag = vec![0; get_syncsafe(&header[6..10])];
+        file.read_exact(&mut tag)?;
+        return Ok(find_geob(&tag, header[3], header[5]).map(<[u8]>::to_vec));
+    }
+    if header.starts_with(b"fLaC") {
+        file.seek(SeekFrom::Start(4))?;
+        return read_flac(&mut file);
+    }
+    Ok(None)
+}
+
+/// Walk the metadata blocks at the start of a FLAC file
+fn read_flac(file: &mut File) -> io::Result<Option<Vec<u8>>> {
+    loop {
+        let mut header = [0; 4];
+        file.read_exact(&mut header)?;
+        let length = get_size(&header[1..]);
+        if header[0] & 0x7F == FLAC_VORBIS_COMMENT {
+            let mut block = vec![0; length];
+            file.read_exact(&mut block)?;
+            return Ok(find_vorbis_comment(&block));
+        }
+        if header[0] & 0x80 != 0 {
+            return Ok(None);
+        }
+        file.seek(SeekFrom::Current(i64::try_from(length).unwrap_or(i64::MAX)))?;
+    }
+}
+
+/// Find the markers frame in the body of an ID3v2.3 or ID3v2.4 tag
+fn find_geob(mut tag: &[u8], version: u8, flags: u8) -> Option<&[u8]> {
+    if !matches!(version, 3 | 4) {
+        return None;
+    }
+    // Skip the extended header, whose size includes itself only in ID3v2.4
+    if flags & 0x40 != 0 {
+        let size = match version {
+            3 => get_size(tag.get(..4)?) + 4,
+            _ => get_syncsafe(tag.get(..4)?),
+        };
+        tag = tag.get(size..)?;
+    }
+    while let Some(header) = tag.get(..10).filter(|header| header[0] != 0) {
+        let size = match version {
+            3 => get_size(&header[4..8]),
+            _ => get_syncsafe(&header[4..8]),
+        };
+        let body = tag.get(10..10 + size)?;
+        if &header[..4] == b"GEOB" {
+            if let Some(data) = parse_geob(body) {
+                return Some(data);
+            }
+        }
+        tag = &tag[10 + size..];
+    }
+    None
+}
+
+/// Split a `GEOB` frame into its MIME type, file name, description and data,
+/// of which Serato only writes Latin-1 text
+fn parse_geob(body: &[u8]) -> Option<&[u8]> {
+    if body.first() != Some(&0) {
+        return None;
+    }
+    let mut rest = &body[1..];
+    let mut fields = [&[][..]; 3];
+    for field in &mut fields {
+        let end = rest.iter().position(|byte| *byte == 0)?;
+        *field = &rest[..end];
+        rest = &rest[end + 1..];
+    }
+    (fields[2] == GEOB_DESCRIPTION).then_some(rest)
+}
+
+/// Find the markers in a Vorbis comment block, which holds little endian
+/// lengths followed by text
+fn find_vorbis_comment(block: &[u8]) -> Option<Vec<u8>> {
+    let read_length = |at: usize| {
+        let bytes = block.get(at..at + 4)?.try_into().ok()?;
+        usize::try_from(u32::from_le_bytes(bytes)).ok()
+    };
+    let mut at = 4 + read_length(0)?;
+    let count = read_length(at)?;
+    at += 4;
+    for _ in 0..count {
+        let length = read_length(at)?;
+        let comment = block.get(at + 4..at + 4 + length)?;
+        at += 4 + length;
+        let Some(split) = comment.iter().position(|byte| *byte == b'=') else {
+            continue;
+        };
+        if !comment[..split].eq_ignore_ascii_case(VORBIS_COMMENT.as_bytes()) {
+            continue;
+        }
+        let decoded = decode_base64(&comment[split + 1..])?;
+        let start = decoded
+            .windows(GEOB_DESCRIPTION.len() + 1)
+            .position(|window| window.starts_with(GEOB_DESCRIPTION) && window.ends_with(&[0]))?;
+        return Some(decoded[start + GEOB_DESCRIPTION.len() + 1..].to_vec());
+    }
+    None
+}
+
+fn get_size(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 8) | usize::from(*byte))
+}
+
+/// Sizes in `ID3v2` tags use only the lower seven bits of each byte
+fn get_syncsafe(bytes: &[u8]) -> usize {
+    bytes
+        .iter()
+        .fold(0, |size, byte| (size << 7) | usize::from(byte & 0x7F))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_geob_frame() {
+        let body = [
+            &[0][..],
+            b"application/octet-stream\0\0Serato Markers2\0",
+            &[1, 1, b'A'],
+        ]
+        .concat();
+        let frame = |id: &[u8], body: &[u8]| {
+            let size = u32::try_from(body.len()).unwrap().to_be_bytes();
+            [id, &size, &[0, 0], body].concat()
+        };
+        let tag = [
+            frame(b"TIT2", b"\0Song"),
+            frame(
+                b"GEOB",
+                &[&[0][..], b"application/octet-stream\0\0Serato Overview\0x"].concat(),
+            ),
+            frame(b"GEOB", &body),
+            vec![0; 8],
+        ]
+        .concat();
+        assert_eq!(find_geob(&tag, 3, 0), Some(&[1, 1, b'A'][..]));
+        assert_eq!(find_geob(&tag, 2, 0), None);
+    }
+
+    #[test]
+    fn finds_vorbis_comment() {
+        let comment = |text: &str| {
+            let length = u32::try_from(text.len()).unwrap().to_le_bytes();
+            [&length[..], text.as_bytes()].concat()
+        };
+        // `application/octet-stream\0\0Serato Markers2\0AQFB` in base64
+        let markers =
+            "serato_markers_v2=YXBwbGljYXRpb24vb2N0ZXQtc3RyZWFtAABTZXJhdG8gTWFya2VyczIAAQFB";
+        let block = [
+            comment("vendor"),
+            2u32.to_le_bytes().to_vec(),
+            comment("TITLE=Song"),
+            comment(markers),
+        ]
+        .concat();
+        assert_eq!(find_vorbis_comment(&block), Some(vec![1, 1, b'A']));
+    }
+}
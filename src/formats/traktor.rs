@@ -0,0 +1,506 @@
+//! Traktor `collection.nml` files
+
+use super::{
+    escape_xml, from_stars, get_children, get_free_hotcues, get_stars, Cue, CueKind, Error,
+    Library, Playlist, Result, Track, TreeNode,
+};
+use roxmltree::{Document, Node};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::path::Path;
+
+/// `CUE_V2` types, of which fade markers are treated like regular cues
+const CUE_HOTCUE: &str = "0";
+const CUE_LOAD: &str = "3";
+const CUE_GRID: &str = "4";
+const CUE_LOOP: &str = "5";
+
+/// Number of hot cues that Traktor offers
+const HOTCUE_SLOTS: i32 = 8;
+/// Volume name of the startup disk of a Mac, which paths outside of
+/// `/Volumes` are written to
+const STARTUP_VOLUME: &str = "Macintosh HD";
+
+/// Traktor's fixed track colors, numbered from 1
+const COLORS: [i32; 7] = [
+    0x00FF_0000,
+    0x00FF_8000,
+    0x00FF_FF00,
+    0x0000_FF00,
+    0x0000_80FF,
+    0x0080_00FF,
+    0x00FF_00FF,
+];
+
+pub fn read(path: &Path) -> Result<Library> {
+    parse(&std::fs::read_to_string(path)?)
+}
+
+pub fn parse(contents: &str) -> Result<Library> {
+    let doc = Document::parse(contents.trim_start_matches('\u{feff}'))?;
+    let root = doc.root_element();
+    let Some(collection) = root.children().find(|node| node.has_tag_name("COLLECTION")) else {
+        return Err(Error::Invalid("missing COLLECTION".into()));
+    };
+
+    let mut library = Library::default();
+    let mut by_key = HashMap::new();
+    for node in collection
+        .children()
+        .filter(|node| node.has_tag_name("ENTRY"))
+    {
+        let Some(location) = node.children().find(|child| child.has_tag_name("LOCATION")) else {
+            continue;
+        };
+        let Some((key, track)) = parse_entry(node, location) else {
+            continue;
+        };
+        by_key.insert(key, library.tracks.len());
+        library.tracks.push(track);
+    }
+
+    if let Some(tree) = root.children().find(|node| node.has_tag_name("PLAYLISTS")) {
+        // The single top level node is the `$ROOT` folder
+        for top in tree.children().filter(|node| node.has_tag_name("NODE")) {
+            for node in get_subnodes(top) {
+                collect_playlists(node, &mut Vec::new(), &by_key, &mut library);
+            }
+        }
+    }
+    Ok(library)
+}
+
+pub fn write(path: &Path, library: &Library) -> Result<()> {
+    std::fs::write(path, format(library))?;
+    Ok(())
+}
+
+pub fn format(library: &Library) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\" ?>\n");
+    xml.push_str("<NML VERSION=\"19\"><HEAD COMPANY=\"www.native-instruments.com\" PROGRAM=\"Traktor\"></HEAD>\n");
+    let _ = writeln!(xml, r#"<COLLECTION ENTRIES="{}">"#, library.tracks.len());
+    let keys: Vec<String> = library
+        .tracks
+        .iter()
+        .map(|track| format_entry(&mut xml, track))
+        .collect();
+    xml.push_str("</COLLECTION>\n<PLAYLISTS>\n");
+    let playlists: Vec<&Playlist> = library.playlists.iter().collect();
+    format_node(&mut xml, "$ROOT", &playlists, 0, &keys);
+    xml.push_str("</PLAYLISTS>\n</NML>\n");
+    xml
+}
+
+/// Write an entry and return the key that playlists use to refer to it
+fn format_entry(xml: &mut String, track: &Track) -> String {
+    let tags = &track.tags;
+    let (volume, dir, file) = split_location(&track.location);
+    xml.push_str("<ENTRY");
+    for (name, value) in [("TITLE", &tags.title), ("ARTIST", &tags.artist)] {
+        if let Some(value) = value {
+            let _ = write!(xml, r#" {name}="{}""#, escape_xml(value));
+        }
+    }
+    let _ = write!(
+        xml,
+        r#"><LOCATION DIR="{}" FILE="{}" VOLUME="{}" VOLUMEID=""></LOCATION>"#,
+        escape_xml(&dir),
+        escape_xml(&file),
+        escape_xml(&volume)
+    );
+    if let Some(album) = &tags.album {
+        let _ = write!(xml, r#"<ALBUM TITLE="{}"></ALBUM>"#, escape_xml(album));
+    }
+    xml.push_str("<INFO");
+    for (name, value) in [("GENRE", &tags.genre), ("KEY", &tags.key)] {
+        if let Some(value) = value {
+            let _ = write!(xml, r#" {name}="{}""#, escape_xml(value));
+        }
+    }
+    if let Some(count) = track.play_count {
+        let _ = write!(xml, r#" PLAYCOUNT="{count}""#);
+    }
+    if let Some(duration) = tags.duration {
+        let _ = write!(xml, r#" PLAYTIME="{duration:.0}""#);
+    }
+    if let Some(rating) = track.rating {
+        let _ = write!(xml, r#" RANKING="{}""#, from_stars(rating));
+    }
+    if let Some(color) = track.color {
+        let _ = write!(xml, r#" COLOR="{}""#, get_closest_color(color));
+    }
+    xml.push_str("></INFO>");
+    if let Some(bpm) = tags.bpm {
+        let _ = write!(
+            xml,
+            r#"<TEMPO BPM="{bpm:.6}" BPM_QUALITY="100.000000"></TEMPO>"#
+        );
+        if let Some(first_beat) = tags.first_beat {
+            format_cue(xml, "AutoGrid", CUE_GRID, first_beat, 0.0, -1);
+        }
+    }
+    for cue in &track.cues {
+        let (kind, length) = match (cue.kind, cue.end) {
+            (CueKind::Main, _) => (CUE_LOAD, 0.0),
+            (CueKind::Loop, Some(end)) => (CUE_LOOP, end - cue.start),
+            _ => (CUE_HOTCUE, 0.0),
+        };
+        let hotcue = cue
+            .hotcue
+            .filter(|hotcue| *hotcue < HOTCUE_SLOTS && cue.kind != CueKind::Main)
+            .unwrap_or(-1);
+        format_cue(xml, &cue.label, kind, cue.start, length, hotcue);
+    }
+    xml.push_str("</ENTRY>\n");
+    format!("{volume}{dir}{file}")
+}
+
+/// Positions are written in milliseconds
+fn format_cue(xml: &mut String, name: &str, kind: &str, start: f64, length: f64, hotcue: i32) {
+    let name = if name.is_empty() { "n.n." } else { name };
+    let _ = write!(
+        xml,
+        r#"<CUE_V2 NAME="{}" DISPL_ORDER="0" TYPE="{kind}" START="{:.6}" LEN="{:.6}" REPEATS="-1" HOTCUE="{hotcue}"></CUE_V2>"#,
+        escape_xml(name),
+        start * 1000.0,
+        length * 1000.0
+    );
+}
+
+/// Split a path into the volume, the folders joined by `/:` and the file name
+fn split_location(location: &str) -> (String, String, String) {
+    let (volume, path) = match location.split_once('/') {
+        Some((drive, path)) if is_drive(drive) => (drive.to_owned(), path),
+        _ => match location.strip_prefix("/Volumes/") {
+            Some(rest) => {
+                let (volume, path) = rest.split_once('/').unwrap_or((rest, ""));
+                (volume.to_owned(), path)
+            }
+            None => (STARTUP_VOLUME.to_owned(), location.trim_start_matches('/')),
+        },
+    };
+    let (folders, file) = path.rsplit_once('/').unwrap_or(("", path));
+    let mut dir = String::from("/:");
+    for folder in folders.split('/').filter(|folder| !folder.is_empty()) {
+        dir.push_str(folder);
+        dir.push_str("/:");
+    }
+    (volume, dir, file.to_owned())
+}
+
+fn get_closest_color(color: i32) -> usize {
+    let channels = |color: i32| [(color >> 16) & 0xFF, (color >> 8) & 0xFF, color & 0xFF];
+    let distance = |other: i32| {
+        channels(color)
+            .iter()
+            .zip(channels(other))
+            .map(|(a, b)| (a - b).pow(2))
+            .sum::<i32>()
+    };
+    (1..)
+        .zip(COLORS)
+        .min_by_key(|(_, other)| distance(*other))
+        .map_or(1, |(index, _)| index)
+}
+
+fn format_node(
+    xml: &mut String,
+    name: &str,
+    playlists: &[&Playlist],
+    depth: usize,
+    keys: &[String],
+) {
+    let children = get_children(playlists, depth);
+    let _ = writeln!(
+        xml,
+        r#"<NODE TYPE="FOLDER" NAME="{}"><SUBNODES COUNT="{}">"#,
+        escape_xml(name),
+        children.len()
+    );
+    for child in children {
+        match child {
+            TreeNode::Playlist(playlist_name, playlist) => {
+                let mut path = playlist.path[..depth].to_vec();
+                path.push(playlist_name);
+                let _ = write!(
+                    xml,
+                    r#"<NODE TYPE="PLAYLIST" NAME="{}"><PLAYLIST ENTRIES="{}" TYPE="LIST" UUID="{}">"#,
+                    escape_xml(&path[depth]),
+                    playlist.entries.len(),
+                    get_uuid(&path)
+                );
+                for key in playlist.entries.iter().filter_map(|entry| keys.get(*entry)) {
+                    let _ = write!(
+                        xml,
+                        r#"<ENTRY><PRIMARYKEY TYPE="TRACK" KEY="{}"></PRIMARYKEY></ENTRY>"#,
+                        escape_xml(key)
+                    );
+                }
+                xml.push_str("</PLAYLIST></NODE>\n");
+            }
+            TreeNode::Folder(folder, members) => {
+                format_node(xml, folder, &members, depth + 1, keys);
+            }
+        }
+    }
+    xml.push_str("</SUBNODES></NODE>\n");
+}
+
+/// Traktor only needs playlist ids to be unique, so they are derived from the
+/// path of each playlist with FNV-1a to stay the same across exports and Rust
+/// releases
+fn get_uuid(path: &[String]) -> String {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let hash = path
+        .iter()
+        .flat_map(|name| name.bytes().chain([0]))
+        .fold(OFFSET, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        });
+    format!("{hash:016x}{:016x}", hash.rotate_left(32))
+}
+
+/// Rebuild the path of an entry from its `LOCATION`, returning it along with
+/// the key that playlists use to refer to it
+fn parse_entry(node: Node, location: Node) -> Option<(String, Track)> {
+    let volume = location.attribute("VOLUME").unwrap_or_default();
+    let dir = location.attribute("DIR").unwrap_or_default();
+    let file = location.attribute("FILE")?;
+    let key = format!("{volume}{dir}{file}");
+    let path = dir.replace("/:", "/") + file;
+    let (location, fallback_locations) = match is_drive(volume) {
+        true => (format!("{volume}{path}"), Vec::new()),
+        // macOS volumes are named rather than lettered, and only the startup
+        // volume is mounted at the root
+        false => (path.clone(), vec![format!("/Volumes/{volume}{path}")]),
+    };
+
+    let info = node.children().find(|child| child.has_tag_name("INFO"));
+    let mut cues: Vec<Cue> = node
+        .children()
+        .filter(|child| child.has_tag_name("CUE_V2"))
+        .filter_map(parse_cue)
+        .collect();
+    place_stored_cues(&mut cues);
+    let track = Track {
+        location,
+        fallback_locations,
+        rating: info
+            .and_then(|info| info.attribute("RANKING"))
+            .and_then(|ranking| ranking.parse::<i32>().ok())
+            .map(get_stars),
+        color: info
+            .and_then(|info| info.attribute("COLOR"))
+            .and_then(|color| color.parse::<usize>().ok())
+            .and_then(|color| COLORS.get(color.checked_sub(1)?).copied()),
+        cues,
+        ..Track::default()
+    };
+    Some((key, track))
+}
+
+fn is_drive(volume: &str) -> bool {
+    let mut chars = volume.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.next() == Some(':')
+        && chars.next().is_none()
+}
+
+/// Positions are given in milliseconds
+fn parse_cue(node: Node) -> Option<Cue> {
+    let kind = node.attribute("TYPE").unwrap_or_default();
+    if kind == CUE_GRID {
+        return None;
+    }
+    let start = node.attribute("START")?.parse::<f64>().ok()? / 1000.0;
+    let length = node
+        .attribute("LEN")
+        .and_then(|length| length.parse::<f64>().ok())
+        .map(|length| length / 1000.0)
+        .filter(|length| *length > 0.0);
+    let hotcue = node
+        .attribute("HOTCUE")
+        .and_then(|hotcue| hotcue.parse::<i32>().ok())
+        .filter(|hotcue| *hotcue >= 0);
+    let (kind, end) = match (kind, length) {
+        (CUE_LOOP, Some(length)) => (CueKind::Loop, Some(start + length)),
+        (CUE_LOAD, _) if hotcue.is_none() => (CueKind::Main, None),
+        _ => (CueKind::HotCue, None),
+    };
+    Some(Cue {
+        kind,
+        start,
+        end,
+        hotcue,
+        label: match node.attribute("NAME") {
+            Some("n.n.") | None => String::new(),
+            Some(name) => name.to_owned(),
+        },
+        color: None,
+    })
+}
+
+/// Mixxx has no stored cues outside of hot cues, so they are kept as hot cues
+/// in unused slots
+fn place_stored_cues(cues: &mut [Cue]) {
+    let stored: Vec<usize> = (0..cues.len())
+        .filter(|i| cues[*i].kind != CueKind::Main && cues[*i].hotcue.is_none())
+        .collect();
+    let slots: Vec<i32> = get_free_hotcues(cues).take(stored.len()).collect();
+    for (i, slot) in stored.into_iter().zip(slots) {
+        cues[i].hotcue = Some(slot);
+    }
+}
+
+fn get_subnodes<'a, 'input>(node: Node<'a, 'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    node.children()
+        .filter(|child| child.has_tag_name("SUBNODES"))
+        .flat_map(|subnodes| subnodes.children())
+        .filter(|child| child.has_tag_name("NODE"))
+}
+
+fn collect_playlists(
+    node: Node,
+    path: &mut Vec<String>,
+    by_key: &HashMap<String, usize>,
+    library: &mut Library,
+) {
+    path.push(node.attribute("NAME").unwrap_or_default().to_owned());
+    match node.attribute("TYPE") {
+        Some("PLAYLIST") => {
+            let entries = node
+                .children()
+                .filter(|child| child.has_tag_name("PLAYLIST"))
+                .flat_map(|playlist| playlist.children())
+                .filter(|entry| entry.has_tag_name("ENTRY"))
+                .filter_map(|entry| {
+                    let key = entry
+                        .children()
+                        .find(|child| child.has_tag_name("PRIMARYKEY"))?
+                        .attribute("KEY")?;
+                    by_key.get(key).copied()
+                })
+                .collect();
+            library.playlists.push(Playlist {
+                path: path.clone(),
+                entries,
+            });
+        }
+        Some("FOLDER") => {
+            for child in get_subnodes(node) {
+                collect_playlists(child, path, by_key, library);
+            }
+        }
+        // Smartlists only store their query
+        _ => {}
+    }
+    path.pop();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+
+    const COLLECTION: &str = indoc! {r#"
+        <?xml version="1.0" encoding="UTF-8" standalone="no" ?>
+        <NML VERSION="19"><HEAD COMPANY="www.native-instruments.com" PROGRAM="Traktor"></HEAD>
+        <COLLECTION ENTRIES="2">
+        <ENTRY TITLE="Song" ARTIST="Artist">
+        <LOCATION DIR="/:Users/:dj/:Music/:" FILE="a.mp3" VOLUME="Macintosh HD" VOLUMEID="1"></LOCATION>
+        <INFO RANKING="153" COLOR="4"></INFO>
+        <CUE_V2 NAME="AutoGrid" DISPL_ORDER="0" TYPE="4" START="25.97" LEN="0" REPEATS="-1" HOTCUE="0"></CUE_V2>
+        <CUE_V2 NAME="n.n." DISPL_ORDER="0" TYPE="3" START="1000" LEN="0" REPEATS="-1" HOTCUE="-1"></CUE_V2>
+        <CUE_V2 NAME="Drop" DISPL_ORDER="0" TYPE="0" START="32000" LEN="0" REPEATS="-1" HOTCUE="1"></CUE_V2>
+        <CUE_V2 NAME="Roll" DISPL_ORDER="0" TYPE="5" START="64000" LEN="2000" REPEATS="-1" HOTCUE="-1"></CUE_V2>
+        </ENTRY>
+        <ENTRY TITLE="Other">
+        <LOCATION DIR="/:Music/:" FILE="b.flac" VOLUME="D:" VOLUMEID="2"></LOCATION>
+        </ENTRY>
+        </COLLECTION>
+        <PLAYLISTS><NODE TYPE="FOLDER" NAME="$ROOT"><SUBNODES COUNT="2">
+        <NODE TYPE="FOLDER" NAME="Gigs"><SUBNODES COUNT="1">
+        <NODE TYPE="PLAYLIST" NAME="Friday"><PLAYLIST ENTRIES="2" TYPE="LIST" UUID="x">
+        <ENTRY><PRIMARYKEY TYPE="TRACK" KEY="D:/:Music/:b.flac"></PRIMARYKEY></ENTRY>
+        <ENTRY><PRIMARYKEY TYPE="TRACK" KEY="Macintosh HD/:Users/:dj/:Music/:a.mp3"></PRIMARYKEY></ENTRY>
+        </PLAYLIST></NODE>
+        </SUBNODES></NODE>
+        <NODE TYPE="SMARTLIST" NAME="Smart"><SMARTLIST UUID="y"></SMARTLIST></NODE>
+        </SUBNODES></NODE></PLAYLISTS>
+        </NML>
+    "#};
+
+    #[test]
+    fn rebuilds_locations() {
+        let library = parse(COLLECTION).unwrap();
+        let first = &library.tracks[0];
+        assert_eq!(first.location, "/Users/dj/Music/a.mp3");
+        assert_eq!(
+            first.fallback_locations,
+            ["/Volumes/Macintosh HD/Users/dj/Music/a.mp3"]
+        );
+        assert_eq!(first.rating, Some(3));
+        assert_eq!(first.color, Some(0x0000_FF00));
+        assert_eq!(library.tracks[1].location, "D:/Music/b.flac");
+    }
+
+    #[test]
+    fn converts_cues() {
+        let library = parse(COLLECTION).unwrap();
+        let cues: Vec<_> = library.tracks[0]
+            .cues
+            .iter()
+            .map(|cue| (cue.kind, cue.hotcue, cue.start, cue.end, cue.label.as_str()))
+            .collect();
+        assert_eq!(
+            cues,
+            [
+                (CueKind::Main, None, 1.0, None, ""),
+                (CueKind::HotCue, Some(1), 32.0, None, "Drop"),
+                (CueKind::Loop, Some(0), 64.0, Some(66.0), "Roll"),
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_locations() {
+        let split = |location| {
+            let (volume, dir, file) = split_location(location);
+            format!("{volume}|{dir}|{file}")
+        };
+        assert_eq!(split("/Users/dj/a.mp3"), "Macintosh HD|/:Users/:dj/:|a.mp3");
+        assert_eq!(split("/Volumes/USB/Music/a.mp3"), "USB|/:Music/:|a.mp3");
+        assert_eq!(split("D:/a.mp3"), "D:|/:|a.mp3");
+    }
+
+    #[test]
+    fn derives_stable_uuids() {
+        let path = ["Gigs".to_owned(), "Friday".to_owned()];
+        assert_eq!(get_uuid(&path), "14797592d3f96736d3f9673614797592");
+    }
+
+    #[test]
+    fn writes_what_it_reads() {
+        let library = parse(COLLECTION).unwrap();
+        let written = parse(&format(&library)).unwrap();
+        assert_eq!(written.tracks[0].location, library.tracks[0].location);
+        assert_eq!(written.tracks[1].location, library.tracks[1].location);
+        assert_eq!(written.tracks[0].rating, Some(3));
+        assert_eq!(written.tracks[0].color, Some(0x0000_FF00));
+        assert_eq!(written.tracks[0].cues, library.tracks[0].cues);
+        assert_eq!(written.playlists, library.playlists);
+    }
+
+    #[test]
+    fn collects_playlists_but_not_smartlists() {
+        let library = parse(COLLECTION).unwrap();
+        assert_eq!(
+            library.playlists,
+            [Playlist {
+                path: vec!["Gigs".into(), "Friday".into()],
+                entries: vec![1, 0],
+            }]
+        );
+    }
+}
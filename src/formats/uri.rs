@@ -0,0 +1,130 @@
+//! `file://` URIs that collections and playlists store track locations as
+
+use std::fmt::Write;
+
+pub trait DecodeFileUri {
+    /// Turn a `file://` URI into a local path, leaving anything else untouched
+    fn decode_file_uri(self) -> String;
+}
+
+impl DecodeFileUri for &str {
+    fn decode_file_uri(self) -> String {
+        let is_uri = self
+            .get(..7)
+            .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file://"));
+        if !is_uri {
+            return self.to_owned();
+        }
+        let rest = &self[7..];
+        let rest = match rest.get(..10) {
+            Some(host) if host.eq_ignore_ascii_case("localhost/") => &rest[9..],
+            _ => rest,
+        };
+        let decoded = percent_decode(rest);
+        match decoded.strip_prefix('/') {
+            // `file:///C:/Music` keeps a slash before the drive letter
+            Some(path) if has_drive_letter(path) => path.to_owned(),
+            Some(_) => decoded,
+            // Anything else names a remote host, as in `file://server/share`
+            None => format!("//{decoded}"),
+        }
+    }
+}
+
+impl DecodeFileUri for String {
+    fn decode_file_uri(self) -> String {
+        self[..].decode_file_uri()
+    }
+}
+
+/// Decode `%XX` escapes, keeping malformed ones as they are
+pub fn percent_decode(str: &str) -> String {
+    let bytes = str.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == b'%')
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Turn a local path into a `file://localhost/` URI as written by Rekordbox
+/// and iTunes, keeping only unreserved characters, slashes and drive colons
+pub fn encode_file_uri(path: &str) -> String {
+    let path = path.replace('\\', "/");
+    let mut uri = String::from("file://localhost");
+    if !path.starts_with('/') {
+        uri.push('/');
+    }
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' | b':' => {
+                uri.push(char::from(byte));
+            }
+            _ => {
+                let _ = write!(uri, "%{byte:02X}");
+            }
+        }
+    }
+    uri
+}
+
+fn has_drive_letter(path: &str) -> bool {
+    let mut chars = path.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.next() == Some(':')
+        && matches!(chars.next(), None | Some('/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_uri() {
+        assert_eq!(
+            encode_file_uri("/home/dj/Music/Café Del Mar.mp3"),
+            "file://localhost/home/dj/Music/Caf%C3%A9%20Del%20Mar.mp3"
+        );
+        assert_eq!(
+            encode_file_uri("C:\\Music\\a&b.mp3"),
+            "file://localhost/C:/Music/a%26b.mp3"
+        );
+    }
+
+    #[test]
+    fn decodes_unix_uri() {
+        let result = "file:///home/dj/Music/Caf%C3%A9%20Del%20Mar.mp3".decode_file_uri();
+        assert_eq!(result, "/home/dj/Music/Café Del Mar.mp3");
+        let result = "FILE://localhost/music/a%2.mp3".decode_file_uri();
+        assert_eq!(result, "/music/a%2.mp3");
+    }
+
+    #[test]
+    fn decodes_windows_uri() {
+        assert_eq!("file:///C:/Music/a.mp3".decode_file_uri(), "C:/Music/a.mp3");
+        assert_eq!(
+            "file://server/share/a.mp3".decode_file_uri(),
+            "//server/share/a.mp3"
+        );
+    }
+
+    #[test]
+    fn leaves_paths_alone() {
+        assert_eq!("../Music/%20.mp3".decode_file_uri(), "../Music/%20.mp3");
+    }
+}
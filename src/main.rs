@@ -1,6 +1,7 @@
 mod cli;
 mod database;
 mod error;
+mod formats;
 
 use clap::Parser;
 use cli::commands::Command;
@@ -24,6 +25,10 @@ async fn main() -> Result<(), CustomUserError> {
         .log_to_stdout()
         .start()?;
 
+    if let Some(path) = &cli.settings_path {
+        database::settings::set_override(path);
+    }
+
     match cli.command {
         Some(cmd) => cmd.run().await,
         None => match prompt()? {